@@ -15,17 +15,23 @@
 ///        println!("Page {} Item {}", result.title, result.qid);
 ///     });
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, Site, Tool, ToolRequest, ToolResponse, ToolsError,
+    ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
-use serde_json::{Value, json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct AListBuildingToolResult {
     pub title: String,
     pub qid: String,
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AListBuildingTool {
     site: Site,
     q: String,
@@ -52,51 +58,34 @@ impl AListBuildingTool {
     pub fn q(&self) -> &str {
         &self.q
     }
-
-    pub async fn as_json(&self) -> Value {
-        let site = self.site();
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.results()
-                .iter()
-                .map(|result| (FancyTitle::from_prefixed(&result.title, &api).to_json(),result))
-                .map(|(mut v,result)| {v["wikidata"] = json!(result.qid); v})
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
-    }
 }
 
 #[async_trait]
 impl Tool for AListBuildingTool {
-    #[cfg(feature = "blocking")]
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
+    type Output = Vec<AListBuildingToolResult>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
         let url = format!(
-            "https://a-list-bulding-tool.toolforge.org/API/?wiki_db={wiki}&QID={q}",
+            "{base}/API/?wiki_db={wiki}&QID={q}",
+            base = ti.endpoint(Endpoint::AListBuildingTool),
             wiki = self.site.wiki(),
             q = self.q
         );
-        let client = crate::ToolsInterface::blocking_client()?;
-        let json = client.get(&url).send()?.json()?;
-        self.set_from_json(json)
+        Ok(ToolRequest::get(url))
     }
 
-    #[cfg(feature = "tokio")]
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = format!(
-            "https://a-list-bulding-tool.toolforge.org/API/?wiki_db={wiki}&QID={q}",
-            wiki = self.site.wiki(),
-            q = self.q
-        );
-        let client = crate::ToolsInterface::tokio_client()?;
-        let json = client.get(&url).send().await?.json().await?;
-        self.set_from_json(json)
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.results.clone())
     }
+}
 
+impl AListBuildingTool {
     fn set_from_json(&mut self, j: Value) -> Result<(), ToolsError> {
+        self.results.clear();
         for entry in j
             .as_array()
-            .ok_or_else(|| ToolsError::Json("Result is not an array".to_string()))?
+            .ok_or_else(|| ToolsError::parse("AListBuildingTool", "result", "not an array"))?
         {
             let title = match entry.get("title") {
                 Some(title) => match title.as_str() {
@@ -122,11 +111,32 @@ impl Tool for AListBuildingTool {
     }
 }
 
-// DEACTIVATED WHILE IT ONLY THROWS 500 ERRORS
-/*
+impl PageListSource for AListBuildingTool {
+    fn page_list_site(&self) -> Option<Site> {
+        Some(self.site.clone())
+    }
+
+    fn page_list_with(&self, namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let pages = self
+            .results
+            .iter()
+            .map(|result| {
+                let meta = PageMeta {
+                    wikidata: Some(result.qid.to_owned()),
+                    ..Default::default()
+                };
+                Page::from_prefixed(&result.title, namespaces, meta)
+            })
+            .collect();
+        Ok(PageList::new(self.site.clone(), pages))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::replay::Replay;
 
     #[test]
     fn test_new() {
@@ -137,16 +147,18 @@ mod tests {
         assert_eq!(tool.q(), q);
     }
 
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_alistbuildingtool_json() {
+        let replay = Replay::start("a_list_building_tool_q42").await;
         let site = Site::from_wiki("enwiki").unwrap();
         let q = "Q42";
         let mut tool = AListBuildingTool::new(site, q);
-        tool.run().await.unwrap();
-        assert!(tool
-            .results()
-            .iter()
-            .any(|result| result.qid == "Q5" && result.title == "Human"));
+        tool.run_with(&replay).await.unwrap();
+        assert!(
+            tool.results()
+                .iter()
+                .any(|result| result.qid == "Q5" && result.title == "Human")
+        );
     }
 }
-*/
@@ -20,6 +20,22 @@
 //! ti missing_topics --wiki dewiki --article Biologie --no_template_links
 //! ```
 //!
+//! Example: Re-run a saved query from a JSON or TOML file (see `QuerySpec`):
+//! ```shell
+//! ti query weekly_missing_topics.toml
+//! ```
+//!
+//! Example: Update the sitematrix snapshot bundled with the library:
+//! ```shell
+//! ti sitematrix > data/sitematrix.json
+//! ```
+//!
+//! To see which requests are sent, and how long they take, use `--verbose`
+//! (requires the `tracing` feature, e.g. `cargo install tools_interface --features tracing`):
+//! ```shell
+//! ti --verbose petscan --id 28348714
+//! ```
+//!
 //! To convert the output to a more human-readable format, you can use `jq`:
 //! ```shell
 //! # First, pipe your output to a file:
@@ -33,9 +49,10 @@
 use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
 use serde_json::Value;
 use tools_interface::{
-    AListBuildingTool, Completer, CompleterFilter, Duplicity, MissingTopics, PagePile, PetScan,
-    Site, Tool, grep::Grep, list_building::ListBuilding, page_list::PageList, search::WikiSearch,
-    wiki_nearby::WikiNearby, xtools_pages::XtoolsPages,
+    AListBuildingTool, Completer, CompleterFilter, Duplicity, MissingTopics, PageListSource,
+    PagePile, PetScan, QuerySpec, Site, SiteRegistry, Tool, ToolsInterface, grep::Grep,
+    list_building::ListBuilding, page_list::PageList, search::WikiSearch, wiki_nearby::WikiNearby,
+    xtools_pages::XtoolsPages,
 };
 
 fn write_json(j: &Value) {
@@ -52,6 +69,11 @@ fn write_output(out: &Value, params_all: &ArgMatches) {
     }
 }
 
+async fn write_page_list<T: PageListSource>(tool: &T, params_all: &ArgMatches) {
+    let out = tool.page_list().await.unwrap().as_json().await;
+    write_output(&out, params_all);
+}
+
 async fn alistbuildingtool(params_all: &ArgMatches) {
     let params = params_all
         .subcommand_matches("alistbuildingtool")
@@ -63,8 +85,7 @@ async fn alistbuildingtool(params_all: &ArgMatches) {
         .to_ascii_uppercase();
     let mut tool = AListBuildingTool::new(Site::from_wiki(wiki).unwrap(), &qid);
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
 }
 
 async fn listbuilding(params_all: &ArgMatches) {
@@ -75,8 +96,7 @@ async fn listbuilding(params_all: &ArgMatches) {
     let title = params.get_one::<String>("title").expect("--title missing");
     let mut tool = ListBuilding::new(Site::from_wiki(wiki).unwrap(), title);
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
 }
 
 async fn wikinearby(params_all: &ArgMatches) {
@@ -100,8 +120,7 @@ async fn wikinearby(params_all: &ArgMatches) {
         tool.set_offset(*offset);
     }
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
 }
 
 async fn xtools_pages(params_all: &ArgMatches) {
@@ -115,8 +134,7 @@ async fn xtools_pages(params_all: &ArgMatches) {
     let site = Site::from_wiki(wiki).unwrap();
     let mut tool = XtoolsPages::new(site, user).with_namespace_id(*namespace_id);
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
 }
 
 async fn completer(params_all: &ArgMatches) {
@@ -149,8 +167,7 @@ async fn completer(params_all: &ArgMatches) {
         });
     }
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
 }
 
 async fn duplicity(params_all: &ArgMatches) {
@@ -160,8 +177,14 @@ async fn duplicity(params_all: &ArgMatches) {
     let wiki = params.get_one::<String>("wiki").expect("--wiki missing");
     let mut tool = Duplicity::new(Site::from_wiki(wiki).unwrap());
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
+}
+
+async fn sitematrix() {
+    let json = SiteRegistry::fetch_json(&ToolsInterface::global())
+        .await
+        .unwrap();
+    write_json(&json);
 }
 
 async fn search(params_all: &ArgMatches) {
@@ -172,8 +195,7 @@ async fn search(params_all: &ArgMatches) {
     let query = params.get_one::<String>("query").expect("--query missing");
     let mut tool = WikiSearch::new(Site::from_wiki(wiki).unwrap(), query);
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
 }
 
 async fn subset(params_all: &ArgMatches) {
@@ -184,7 +206,7 @@ async fn subset(params_all: &ArgMatches) {
     let file2 = params.get_one::<String>("file2").expect("--file2 missing");
     let pages1 = PageList::from_file(file1).unwrap();
     let pages2 = PageList::from_file(file2).unwrap();
-    let result = pages1.subset(&pages2).await;
+    let result = pages1.subset(&pages2).await.unwrap();
     let out = result.as_json().await;
     write_output(&out, params_all);
 }
@@ -197,7 +219,20 @@ async fn union(params_all: &ArgMatches) {
     let file2 = params.get_one::<String>("file2").expect("--file2 missing");
     let pages1 = PageList::from_file(file1).unwrap();
     let pages2 = PageList::from_file(file2).unwrap();
-    let result = pages1.union(&pages2).await;
+    let result = pages1.union(&pages2).await.unwrap();
+    let out = result.as_json().await;
+    write_output(&out, params_all);
+}
+
+async fn diff(params_all: &ArgMatches) {
+    let params = params_all
+        .subcommand_matches("diff")
+        .expect("No subcommand matches found");
+    let file1 = params.get_one::<String>("file1").expect("--file1 missing");
+    let file2 = params.get_one::<String>("file2").expect("--file2 missing");
+    let pages1 = PageList::from_file(file1).unwrap();
+    let pages2 = PageList::from_file(file2).unwrap();
+    let result = pages1.diff(&pages2).unwrap();
     let out = result.as_json().await;
     write_output(&out, params_all);
 }
@@ -209,8 +244,7 @@ async fn pagepile(params_all: &ArgMatches) {
     let id = params.get_one::<u32>("id").expect("--id missing");
     let mut tool = PagePile::new(*id);
     tool.run().await.unwrap();
-    let out = tool.as_json().await.unwrap();
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
 }
 
 async fn petscan(params_all: &ArgMatches) {
@@ -236,7 +270,17 @@ async fn petscan(params_all: &ArgMatches) {
             .push((key.to_string(), value.to_string())); // Add new value
     }
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
+    write_page_list(&tool, params_all).await;
+}
+
+async fn query(params_all: &ArgMatches) {
+    let params = params_all
+        .subcommand_matches("query")
+        .expect("No subcommand matches found");
+    let file = params.get_one::<String>("file").expect("file missing");
+    let spec = QuerySpec::from_file(file).unwrap();
+    let result = spec.run().await.unwrap();
+    let out = result.as_json().await;
     write_output(&out, params_all);
 }
 
@@ -263,8 +307,7 @@ async fn missing_topics(params_all: &ArgMatches) {
         tool = tool.with_category(category, depth);
     }
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
 }
 
 async fn grep(params_all: &ArgMatches) {
@@ -281,8 +324,7 @@ async fn grep(params_all: &ArgMatches) {
     let mut tool = Grep::new(Site::from_wiki(wiki).expect("No such wiki {wiki}"), pattern)
         .with_namespace(*namespace_id);
     tool.run().await.unwrap();
-    let out = tool.as_json().await;
-    write_output(&out, params_all);
+    write_page_list(&tool, params_all).await;
 }
 
 fn get_arg_matches() -> ArgMatches {
@@ -296,6 +338,14 @@ fn get_arg_matches() -> ArgMatches {
                 .long("format")
                 .help("Output format (optional)"),
         )
+        .arg(
+            Arg::new("verbose")
+                .long("verbose")
+                .short('v')
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Print all requests to tools, with status and latency, to stderr"),
+        )
         .subcommands([
             Command::new("alistbuildingtool")
                 .about("Retrieves pages from A List Building Tool")
@@ -373,6 +423,8 @@ fn get_arg_matches() -> ArgMatches {
                         .help("Wiki (eg enwiki)")
                         .required(true),
                 ),
+            Command::new("sitematrix")
+                .about("Retrieves the sitematrix of all Wikimedia wikis, in the format of the bundled snapshot"),
             Command::new("pagepile")
                 .about("Retrieves pages from PagePile")
                 .arg(
@@ -523,6 +575,9 @@ fn get_arg_matches() -> ArgMatches {
                         .value_parser(value_parser!(usize))
                         .required(false),
                 ),
+            Command::new("query")
+                .about("Runs a saved query from a JSON or TOML file, and records the query in the output")
+                .arg(Arg::new("file").required(true).index(1)),
             Command::new("subset")
                 .about("Generates the subset of two JSON output files. Merges metadata for duplicate pages")
                 .arg(Arg::new("file1").required(true).index(1))
@@ -531,23 +586,50 @@ fn get_arg_matches() -> ArgMatches {
                 .about("Generates the union of two JSON output files. Merges metadata for duplicate pages")
                 .arg(Arg::new("file1").required(true).index(1))
                 .arg(Arg::new("file2").required(true).index(2)),
+            Command::new("diff")
+                .about("Compares an older and a newer JSON output file. Lists added, removed and changed pages")
+                .arg(Arg::new("file1").required(true).index(1))
+                .arg(Arg::new("file2").required(true).index(2)),
         ])
         .get_matches()
 }
 
+#[cfg(feature = "tracing")]
+fn init_verbose() {
+    use tracing_subscriber::prelude::*;
+    let filter = tracing_subscriber::filter::Targets::new()
+        .with_default(tracing::Level::WARN)
+        .with_target("tools_interface", tracing::Level::DEBUG);
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(filter)
+        .init();
+}
+
+#[cfg(not(feature = "tracing"))]
+fn init_verbose() {
+    eprintln!("--verbose requires ti to be built with the `tracing` feature");
+}
+
 #[tokio::main]
 async fn main() {
     let m = get_arg_matches();
+    if m.get_flag("verbose") {
+        init_verbose();
+    }
     match m.subcommand_name() {
         Some("alistbuildingtool") => alistbuildingtool(&m).await,
         Some("completer") => completer(&m).await,
+        Some("diff") => diff(&m).await,
         Some("duplicity") => duplicity(&m).await,
         Some("grep") => grep(&m).await,
         Some("listbuilding") => listbuilding(&m).await,
         Some("missing_topics") => missing_topics(&m).await,
         Some("pagepile") => pagepile(&m).await,
         Some("petscan") => petscan(&m).await,
+        Some("query") => query(&m).await,
         Some("search") => search(&m).await,
+        Some("sitematrix") => sitematrix().await,
         Some("subset") => subset(&m).await,
         Some("union") => union(&m).await,
         Some("wikinearby") => wikinearby(&m).await,
@@ -0,0 +1,245 @@
+/// # Cache
+/// An optional response cache for requests sent through a `ToolsInterface`.
+/// Responses are keyed by method, URL (including query parameters) and body.
+/// Only successful responses are stored, and only for tools that have a TTL configured;
+/// all other requests bypass the cache.
+/// Two backends are provided: `MemoryCache` for the lifetime of the process,
+/// and `FileCache` which stores one file per response in a directory, and survives restarts.
+/// Use `ToolsInterface::force_refresh` to skip cache lookups while still updating the cache.
+///
+/// ## Example
+/// ```ignore
+/// let ti = ToolsInterface::builder()
+///     .cache(FileCache::new("/tmp/tools_interface_cache")?)
+///     .cache_ttl(Endpoint::Quarry, Duration::from_secs(3600))
+///     .cache_ttl(Endpoint::Duplicity, Duration::from_secs(86400))
+///     .build()?;
+/// quarry.run_with(&ti).await?; // Downloads, and stores the result
+/// quarry.run_with(&ti).await?; // From the cache
+/// quarry.run_with(&ti.force_refresh()).await?; // Downloads again
+/// ```
+use crate::ToolsError;
+use crate::request::ToolResponse;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::{BufRead, Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A storage backend for cached responses.
+/// Implementations must not return entries that have expired.
+pub trait ResponseCache: Debug + Send + Sync {
+    /// Returns the response stored under `key`, unless it is missing or expired.
+    fn get(&self, key: &str) -> Option<ToolResponse>;
+
+    /// Stores a response under `key`, valid for `ttl`.
+    fn put(&self, key: &str, response: &ToolResponse, ttl: Duration) -> Result<(), ToolsError>;
+
+    /// Removes the response stored under `key`, if any.
+    fn remove(&self, key: &str) -> Result<(), ToolsError>;
+
+    /// Removes all stored responses.
+    fn clear(&self) -> Result<(), ToolsError>;
+}
+
+/// Keeps responses in memory, for the lifetime of the cache.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, (SystemTime, ToolResponse)>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ResponseCache for MemoryCache {
+    fn get(&self, key: &str) -> Option<ToolResponse> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let (expires, response) = entries.get(key)?;
+        if *expires <= SystemTime::now() {
+            entries.remove(key);
+            return None;
+        }
+        Some(response.to_owned())
+    }
+
+    fn put(&self, key: &str, response: &ToolResponse, ttl: Duration) -> Result<(), ToolsError> {
+        let expires = SystemTime::now() + ttl;
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key.to_string(), (expires, response.to_owned()));
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), ToolsError> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.remove(key);
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), ToolsError> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.clear();
+        Ok(())
+    }
+}
+
+/// Metadata line at the start of a cache file; the raw response body follows.
+#[derive(Debug, Serialize, Deserialize)]
+struct FileCacheHeader {
+    key: String,
+    expires: u64,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+/// Stores responses as files in a directory, one file per key.
+#[derive(Debug, Clone)]
+pub struct FileCache {
+    directory: PathBuf,
+}
+
+impl FileCache {
+    /// Uses `directory` for cache files, creating it if necessary.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Result<Self, ToolsError> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    /// Returns the cache file for a key. Keys are hashed with FNV-1a, which is stable across builds.
+    fn path(&self, key: &str) -> PathBuf {
+        let hash = key.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        self.directory.join(format!("{hash:016x}.cache"))
+    }
+
+    fn read(&self, key: &str) -> Option<(FileCacheHeader, Vec<u8>)> {
+        let file = std::fs::File::open(self.path(key)).ok()?;
+        let mut reader = std::io::BufReader::new(file);
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let header: FileCacheHeader = serde_json::from_str(&line).ok()?;
+        let mut body = vec![];
+        reader.read_to_end(&mut body).ok()?;
+        Some((header, body))
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+}
+
+impl ResponseCache for FileCache {
+    fn get(&self, key: &str) -> Option<ToolResponse> {
+        let (header, body) = self.read(key)?;
+        // Different keys can share a file name, so the key is checked as well
+        if header.key != key || header.expires <= Self::now() {
+            return None;
+        }
+        Some(ToolResponse::new(header.status, header.headers, body))
+    }
+
+    fn put(&self, key: &str, response: &ToolResponse, ttl: Duration) -> Result<(), ToolsError> {
+        let header = FileCacheHeader {
+            key: key.to_string(),
+            expires: Self::now().saturating_add(ttl.as_secs()),
+            status: response.status(),
+            headers: response.headers().to_vec(),
+        };
+        let path = self.path(key);
+        // Write to a temporary file first, so concurrent readers never see a partial file
+        let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        let write = || -> std::io::Result<()> {
+            let mut file = std::fs::File::create(&tmp_path)?;
+            serde_json::to_writer(&mut file, &header)?;
+            file.write_all(b"\n")?;
+            file.write_all(response.body())?;
+            std::fs::rename(&tmp_path, &path)
+        };
+        Ok(write()?)
+    }
+
+    fn remove(&self, key: &str) -> Result<(), ToolsError> {
+        match std::fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn clear(&self) -> Result<(), ToolsError> {
+        let entries = std::fs::read_dir(&self.directory)?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "cache") {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response() -> ToolResponse {
+        ToolResponse::new(
+            200,
+            vec![("Content-Type".to_string(), "application/json".to_string())],
+            b"{\"a\":1}\n\xff".to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_memory_cache() {
+        let cache = MemoryCache::new();
+        assert_eq!(cache.get("GET https://example.org"), None);
+        cache
+            .put(
+                "GET https://example.org",
+                &response(),
+                Duration::from_secs(60),
+            )
+            .unwrap();
+        assert_eq!(cache.get("GET https://example.org"), Some(response()));
+        cache
+            .put("GET https://example.org/2", &response(), Duration::ZERO)
+            .unwrap();
+        assert_eq!(cache.get("GET https://example.org/2"), None);
+        cache.clear().unwrap();
+        assert_eq!(cache.get("GET https://example.org"), None);
+    }
+
+    #[test]
+    fn test_file_cache() {
+        let directory = std::env::temp_dir().join(format!(
+            "tools_interface_test_file_cache_{}",
+            std::process::id()
+        ));
+        let cache = FileCache::new(&directory).unwrap();
+        cache
+            .put(
+                "GET https://example.org",
+                &response(),
+                Duration::from_secs(60),
+            )
+            .unwrap();
+        assert_eq!(cache.get("GET https://example.org"), Some(response()));
+        assert_eq!(cache.get("GET https://example.org/other"), None);
+
+        // A new instance on the same directory sees the same entries
+        let cache = FileCache::new(&directory).unwrap();
+        assert_eq!(cache.get("GET https://example.org"), Some(response()));
+        cache.remove("GET https://example.org").unwrap();
+        assert_eq!(cache.get("GET https://example.org"), None);
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -15,14 +15,20 @@
 ///        println!("{title} wanted {count} times");
 ///     });
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, Site, Tool, ToolRequest, ToolResponse, ToolsError,
+    ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 /// This is a filter value for `Completer`.
 /// It can be a category (with depth), a PetScan ID, or a template.
 /// Categories and templates must not have a namespace prefix.
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum CompleterFilter {
     Category { category: String, depth: u32 },
     PetScan { psid: String },
@@ -63,7 +69,8 @@ impl CompleterFilter {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Completer {
     lang_from: String,
     lang_to: String,
@@ -72,7 +79,6 @@ pub struct Completer {
 
     id: u64,
     results: Vec<(String, u64)>,
-    tool_url: String,
 }
 
 impl Completer {
@@ -83,7 +89,6 @@ impl Completer {
         Completer {
             lang_from: lang_from.to_string(),
             lang_to: lang_to.to_string(),
-            tool_url: "https://completer.toolforge.org/data".to_string(),
             ..Default::default()
         }
     }
@@ -109,54 +114,34 @@ impl Completer {
     pub fn results(&self) -> &[(String, u64)] {
         &self.results
     }
-
-    pub async fn as_json(&self) -> Value {
-        let site = Site::from_language_project(&self.lang_to, "wikipedia");
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.results()
-                .iter()
-                .map(|(prefixed_title,counter)| (FancyTitle::from_prefixed(prefixed_title, &api).to_json(),counter))
-                .map(|(mut v,counter)| {v["counter"] = json!(*counter); v})
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
-    }
 }
 
 #[async_trait]
 impl Tool for Completer {
-    #[cfg(feature = "blocking")]
-    /// Run the query in a blocking manner.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        let url = &self.tool_url;
-        let j = self.generate_payload();
-        let client = crate::ToolsInterface::blocking_client()?;
-        let j: Value = client.post(url).json(&j).send()?.json()?;
-        self.set_from_json(j)
+    type Output = Vec<(String, u64)>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        let url = format!("{}/data", ti.endpoint(Endpoint::Completer));
+        Ok(ToolRequest::post(url).json(self.generate_payload()))
     }
 
-    #[cfg(feature = "tokio")]
-    /// Run the query asynchronously.
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = &self.tool_url;
-        let j = self.generate_payload();
-        let client = crate::ToolsInterface::tokio_client()?;
-        let response = client.post(url).json(&j).send().await?;
-        let j: Value = response.json().await?;
-        self.set_from_json(j)
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.results.clone())
     }
+}
 
+impl Completer {
     fn set_from_json(&mut self, j: Value) -> Result<(), ToolsError> {
         if j["success"].as_bool() != Some(true) {
-            return Err(ToolsError::Tool(format!("Completer has failed: {:?}", j)));
+            return Err(ToolsError::tool_status("Completer", j.to_string()));
         }
         self.id = j["meta"]["id"]
             .as_u64()
-            .ok_or(ToolsError::Tool("No ID".to_string()))?;
+            .ok_or_else(|| ToolsError::parse("Completer", "meta.id", "missing"))?;
         self.results = j["data"]
             .as_array()
-            .ok_or(ToolsError::Json("['data'] has no array".into()))?
+            .ok_or_else(|| ToolsError::parse("Completer", "data", "not an array"))?
             .iter()
             .filter_map(|arr| arr.as_array())
             .filter_map(|arr| Some((arr.first()?, arr.get(1)?)))
@@ -165,7 +150,7 @@ impl Tool for Completer {
         Ok(())
     }
 
-    fn generate_payload(&self) -> Value {
+    pub fn generate_payload(&self) -> Value {
         json!({
             "info": {
                 "from": self.lang_from,
@@ -177,9 +162,32 @@ impl Tool for Completer {
     }
 }
 
-#[cfg(test)]
+impl PageListSource for Completer {
+    fn page_list_site(&self) -> Option<Site> {
+        Some(Site::from_language_project(&self.lang_to, "wikipedia"))
+    }
+
+    fn page_list_with(&self, namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let site = Site::from_language_project(&self.lang_to, "wikipedia");
+        let pages = self
+            .results
+            .iter()
+            .map(|(prefixed_title, counter)| {
+                let meta = PageMeta {
+                    counter: Some(*counter),
+                    ..Default::default()
+                };
+                Page::from_prefixed(prefixed_title, namespaces, meta)
+            })
+            .collect();
+        Ok(PageList::new(site, pages))
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
 mod tests {
     use super::*;
+    use crate::replay::Replay;
     use wiremock::matchers::{body_json, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
@@ -206,8 +214,11 @@ mod tests {
                 depth: 0,
             })
             .ignore_cache();
-        c.tool_url = format!("{}/data", mock_server.uri());
-        c.run().await.unwrap();
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::Completer, mock_server.uri())
+            .build()
+            .unwrap();
+        c.run_with(&ti).await.unwrap();
         assert_eq!(c.id(), 6623);
         assert_eq!(
             c.results(),
@@ -217,4 +228,20 @@ mod tests {
             ]
         );
     }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_completer_replay() {
+        let replay = Replay::start("completer_biologie").await;
+        let mut c = Completer::new("de", "en")
+            .filter(CompleterFilter::Category {
+                category: "Biologie".to_string(),
+                depth: 0,
+            })
+            .ignore_cache();
+        let results = c.run_with(&replay).await.unwrap();
+        assert_eq!(c.id(), 6623);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1], ("Zustandsänderung".to_string(), 1));
+    }
 }
@@ -18,12 +18,17 @@
 ///        println!("{} was added {}",result.title, result.creation_date);
 ///     });
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, Site, Tool, ToolRequest, ToolResponse, ToolsError,
+    ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct DuplicityResult {
     pub title: String,
     pub creation_date: NaiveDateTime,
@@ -33,16 +38,14 @@ impl DuplicityResult {
     fn from_json(j: &Value) -> Result<Self, ToolsError> {
         let title = j["title"]
             .as_str()
-            .ok_or_else(|| ToolsError::Json("DuplicityResult title is not a string".to_string()))?
+            .ok_or_else(|| ToolsError::parse("Duplicity", "title", "not a string"))?
             .to_string();
 
-        let creation_date = j["creation_date"].as_str().ok_or_else(|| {
-            ToolsError::Json("DuplicityResult creation_date is not a string".to_string())
-        })?;
-        let creation_date =
-            NaiveDateTime::parse_from_str(creation_date, "%Y%m%d%H%M%S").map_err(|e| {
-                ToolsError::Json(format!("DuplicityResult creation_date parse error: {}", e))
-            })?;
+        let creation_date = j["creation_date"]
+            .as_str()
+            .ok_or_else(|| ToolsError::parse("Duplicity", "creation_date", "not a string"))?;
+        let creation_date = NaiveDateTime::parse_from_str(creation_date, "%Y%m%d%H%M%S")
+            .map_err(|e| ToolsError::parse("Duplicity", "creation_date", e.to_string()))?;
         Ok(Self {
             title,
             creation_date,
@@ -50,21 +53,49 @@ impl DuplicityResult {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Duplicity {
     site: Site,
     results: Vec<DuplicityResult>,
 }
 
 impl Duplicity {
+    #[cfg(feature = "tokio")]
+    /// Returns all wikis known to Duplicity, with the number of pages without Wikidata item.
     pub async fn wikis() -> Result<Vec<(String, u64)>, ToolsError> {
-        let url = "https://wikidata-todo.toolforge.org/duplicity/api.php?action=wikis";
-        let client = crate::ToolsInterface::tokio_client()?;
-        let response = client.get(url).send().await?;
-        let j: Value = response.json().await?;
+        Self::wikis_with(&ToolsInterface::global()).await
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Same as `wikis`, using the given `ToolsInterface`.
+    pub async fn wikis_with(ti: &ToolsInterface) -> Result<Vec<(String, u64)>, ToolsError> {
+        let response = ti.execute_checked(&Self::wikis_request(ti)).await?;
+        Self::parse_wikis(response.json()?)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `wikis`, in a blocking manner.
+    pub fn wikis_blocking() -> Result<Vec<(String, u64)>, ToolsError> {
+        Self::wikis_blocking_with(&ToolsInterface::global())
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `wikis_blocking`, using the given `ToolsInterface`.
+    pub fn wikis_blocking_with(ti: &ToolsInterface) -> Result<Vec<(String, u64)>, ToolsError> {
+        let response = ti.execute_checked_blocking(&Self::wikis_request(ti))?;
+        Self::parse_wikis(response.json()?)
+    }
+
+    fn wikis_request(ti: &ToolsInterface) -> ToolRequest {
+        let url = format!("{}/api.php?action=wikis", ti.endpoint(Endpoint::Duplicity));
+        ToolRequest::get(url)
+    }
+
+    fn parse_wikis(j: Value) -> Result<Vec<(String, u64)>, ToolsError> {
         let ret = j["wikis"]
             .as_array()
-            .ok_or_else(|| ToolsError::Json("['wikis'] is not an array".to_string()))?
+            .ok_or_else(|| ToolsError::parse("Duplicity", "wikis", "not an array"))?
             .iter()
             .filter_map(|x| {
                 let wiki = x.get("wiki")?.as_str()?.to_string();
@@ -89,24 +120,26 @@ impl Duplicity {
     pub fn results(&self) -> &[DuplicityResult] {
         &self.results
     }
-
-    pub async fn as_json(&self) -> Value {
-        let site = self.site();
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.results()
-                .iter()
-                .map(|result| (FancyTitle::from_prefixed(&result.title, &api).to_json(),result))
-                .map(|(mut v,result)| {v["added_to_tool"] = json!(format!("{}",result.creation_date.format("%Y-%m-%d %H:%M:%S"))); v})
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
-    }
 }
 
 #[async_trait]
 impl Tool for Duplicity {
-    fn generate_paramters(&self) -> Result<Vec<(String, String)>, ToolsError> {
+    type Output = Vec<DuplicityResult>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        let url = format!("{}/api.php", ti.endpoint(Endpoint::Duplicity));
+        let parameters = self.generate_paramters()?;
+        Ok(ToolRequest::get(url).query(&parameters))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.results.clone())
+    }
+}
+
+impl Duplicity {
+    pub fn generate_paramters(&self) -> Result<Vec<(String, String)>, ToolsError> {
         let parameters: Vec<(String, String)> = [
             ("action".to_string(), "articles".to_string()),
             ("wiki".to_string(), self.site.wiki().to_string()),
@@ -115,37 +148,16 @@ impl Tool for Duplicity {
         Ok(parameters)
     }
 
-    #[cfg(feature = "blocking")]
-    /// Run the query in a blocking manner.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        let url = "https://wikidata-todo.toolforge.org/duplicity/api.php";
-        let parameters = self.generate_paramters()?;
-        let client = crate::ToolsInterface::blocking_client()?;
-        let j: Value = client.get(url).query(&parameters).send()?.json()?;
-        self.set_from_json(j)
-    }
-
-    #[cfg(feature = "tokio")]
-    /// Run the query asynchronously.
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = "https://wikidata-todo.toolforge.org/duplicity/api.php";
-        let parameters = self.generate_paramters()?;
-        let client = crate::ToolsInterface::tokio_client()?;
-        let response = client.get(url).query(&parameters).send().await?;
-        let j: Value = response.json().await?;
-        self.set_from_json(j)
-    }
-
     fn set_from_json(&mut self, j: Value) -> Result<(), ToolsError> {
         if j["status"].as_str() != Some("OK") {
-            return Err(ToolsError::Tool(format!(
-                "MissingTopics status is not OK: {:?}",
-                j["status"]
-            )));
+            return Err(ToolsError::tool_status(
+                "Duplicity",
+                j["status"].to_string(),
+            ));
         }
         self.results = j["articles"]
             .as_array()
-            .ok_or_else(|| ToolsError::Json("['results'] is not an array".to_string()))?
+            .ok_or_else(|| ToolsError::parse("Duplicity", "articles", "not an array"))?
             .iter()
             .map(DuplicityResult::from_json)
             .collect::<Result<Vec<DuplicityResult>, ToolsError>>()?;
@@ -153,14 +165,35 @@ impl Tool for Duplicity {
     }
 }
 
-#[cfg(test)]
+impl PageListSource for Duplicity {
+    fn page_list_site(&self) -> Option<Site> {
+        Some(self.site.clone())
+    }
+
+    fn page_list_with(&self, namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let pages = self
+            .results
+            .iter()
+            .map(|result| {
+                let added = result.creation_date.format("%Y-%m-%d %H:%M:%S").to_string();
+                let meta = PageMeta::default().with_extra("added_to_tool", json!(added));
+                Page::from_prefixed(&result.title, namespaces, meta)
+            })
+            .collect();
+        Ok(PageList::new(self.site.clone(), pages))
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
 mod tests {
     use super::*;
+    use crate::replay::Replay;
 
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_duplicity_wikis_async() {
-        let wikis = Duplicity::wikis().await.unwrap();
+        let replay = Replay::start("duplicity_wikis").await;
+        let wikis = Duplicity::wikis_with(&replay).await.unwrap();
         assert!(wikis.len() > 300);
         assert!(wikis.iter().any(|(wiki, _count)| wiki == "kswiki"));
     }
@@ -168,10 +201,12 @@ mod tests {
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_duplicity_run_async() {
+        let replay = Replay::start("duplicity_enwiki").await;
         let mut d = Duplicity::new(Site::from_wiki("enwiki").unwrap());
-        d.run().await.unwrap();
+        d.run_with(&replay).await.unwrap();
         assert_eq!(d.site().language(), "en");
         assert_eq!(d.site().project(), "wikipedia");
-        assert!(d.results().len() > 1000); // enwiki is usually bad.
+        assert_eq!(d.results().len(), 3);
+        assert_eq!(d.results()[1].title, "Beata Szydłowska");
     }
 }
@@ -0,0 +1,159 @@
+/// # Endpoint
+/// Base URLs of the tools and APIs used by this crate.
+/// Each base URL can be overridden in a `ToolsInterface`,
+/// e.g. to point a tool at a mirror, a staging instance, or a local mock server.
+///
+/// ## Example
+/// ```ignore
+/// let ti = ToolsInterface::builder()
+///     .endpoint(Endpoint::PetScan, "http://localhost:8000")
+///     .build()
+///     .unwrap();
+/// let mut ps = PetScan::new(12345);
+/// ps.run_with(&ti).await.unwrap(); // Queries http://localhost:8000/?psid=12345&...
+/// ```
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    AListBuildingTool,
+    Completer,
+    Duplicity,
+    Grep,
+    ListBuilding,
+    MissingTopics,
+    PagePile,
+    Pageviews,
+    PersondataTemplates,
+    PetScan,
+    Quarry,
+    QuickStatements,
+    SiteMatrix,
+    SparqlRC,
+    WdInfernal,
+    WikiNearby,
+    XtoolsPages,
+}
+
+impl Endpoint {
+    /// All endpoints.
+    pub const ALL: [Endpoint; 17] = [
+        Self::AListBuildingTool,
+        Self::Completer,
+        Self::Duplicity,
+        Self::Grep,
+        Self::ListBuilding,
+        Self::MissingTopics,
+        Self::PagePile,
+        Self::Pageviews,
+        Self::PersondataTemplates,
+        Self::PetScan,
+        Self::Quarry,
+        Self::QuickStatements,
+        Self::SiteMatrix,
+        Self::SparqlRC,
+        Self::WdInfernal,
+        Self::WikiNearby,
+        Self::XtoolsPages,
+    ];
+
+    /// Returns the public base URL of the tool, without trailing slash.
+    pub fn default_url(&self) -> &'static str {
+        match self {
+            Self::AListBuildingTool => "https://a-list-bulding-tool.toolforge.org",
+            Self::Completer => "https://completer.toolforge.org",
+            Self::Duplicity => "https://wikidata-todo.toolforge.org/duplicity",
+            Self::Grep => "https://grep.toolforge.org",
+            Self::ListBuilding => "https://list-building.toolforge.org",
+            Self::MissingTopics => "https://missingtopics.toolforge.org",
+            Self::PagePile => "https://pagepile.toolforge.org",
+            Self::Pageviews => "https://wikimedia.org/api/rest_v1",
+            Self::PersondataTemplates => "https://persondata.toolforge.org/vorlagen",
+            Self::PetScan => "https://petscan.wmflabs.org",
+            Self::Quarry => "https://quarry.wmcloud.org",
+            Self::QuickStatements => "https://quickstatements.toolforge.org",
+            Self::SiteMatrix => "https://meta.wikimedia.org/w/api.php",
+            Self::SparqlRC => "https://wikidata-todo.toolforge.org",
+            Self::WdInfernal => "https://wd-infernal.toolforge.org",
+            Self::WikiNearby => "https://wikinearby.toolforge.org",
+            Self::XtoolsPages => "https://xtools.wmcloud.org",
+        }
+    }
+}
+
+/// A registry of base URL overrides. Endpoints without override use `Endpoint::default_url`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Endpoints {
+    overrides: HashMap<Endpoint, String>,
+}
+
+impl Endpoints {
+    /// Overrides the base URL for an endpoint. A trailing slash is removed.
+    pub fn set<S: Into<String>>(&mut self, endpoint: Endpoint, base_url: S) {
+        let base_url: String = base_url.into();
+        let base_url = base_url.trim_end_matches('/').to_string();
+        self.overrides.insert(endpoint, base_url);
+    }
+
+    /// Removes the override for an endpoint.
+    pub fn reset(&mut self, endpoint: Endpoint) {
+        self.overrides.remove(&endpoint);
+    }
+
+    /// Returns the base URL for an endpoint, without trailing slash.
+    pub fn url(&self, endpoint: Endpoint) -> &str {
+        self.overrides
+            .get(&endpoint)
+            .map(|s| s.as_str())
+            .unwrap_or_else(|| endpoint.default_url())
+    }
+
+    /// Returns the endpoint a URL belongs to. If several base URLs match, the longest one wins.
+    pub fn endpoint_for_url(&self, url: &str) -> Option<Endpoint> {
+        Endpoint::ALL
+            .into_iter()
+            .filter(|endpoint| {
+                let base_url = self.url(*endpoint);
+                url.strip_prefix(base_url)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+            })
+            .max_by_key(|endpoint| self.url(*endpoint).len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoints() {
+        let mut endpoints = Endpoints::default();
+        assert_eq!(
+            endpoints.url(Endpoint::PetScan),
+            "https://petscan.wmflabs.org"
+        );
+        endpoints.set(Endpoint::PetScan, "http://localhost:8000/");
+        assert_eq!(endpoints.url(Endpoint::PetScan), "http://localhost:8000");
+        assert_eq!(
+            endpoints.url(Endpoint::Quarry),
+            "https://quarry.wmcloud.org"
+        );
+        assert_eq!(
+            endpoints.endpoint_for_url("http://localhost:8000/?psid=1"),
+            Some(Endpoint::PetScan)
+        );
+        assert_eq!(
+            endpoints.endpoint_for_url("https://wikidata-todo.toolforge.org/duplicity/api.php"),
+            Some(Endpoint::Duplicity)
+        );
+        assert_eq!(
+            endpoints.endpoint_for_url("https://wikidata-todo.toolforge.org/sparql_rc.php"),
+            Some(Endpoint::SparqlRC)
+        );
+        endpoints.reset(Endpoint::PetScan);
+        assert_eq!(
+            endpoints.url(Endpoint::PetScan),
+            "https://petscan.wmflabs.org"
+        );
+    }
+}
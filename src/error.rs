@@ -1,12 +1,21 @@
 /// # Error module
 /// `ToolsError` is a wrapper around several error types that can be returned by the tools.
+/// Failures are reported as specific variants where possible, so callers can branch on them:
+/// `HttpStatus` for unexpected HTTP responses, `RateLimited` for HTTP 429,
+/// `ToolStatus` when a tool reports a failure in its response, `Parse` for unexpected response content,
+/// and `Timeout` for requests that took too long.
+/// Wrapped errors are available via `Error::source`.
 use std::{
     error::Error,
     fmt::{self, Display, Formatter},
+    time::Duration,
 };
 
 use mediawiki::media_wiki_error::MediaWikiError;
 
+/// Maximum number of characters of a response body kept in `ToolsError::HttpStatus`.
+const BODY_EXCERPT_MAX_CHARS: usize = 500;
+
 #[derive(Debug)]
 pub enum ToolsError {
     Tool(String),
@@ -16,6 +25,82 @@ pub enum ToolsError {
     SerdeJson(serde_json::Error),
     MediaWiki(MediaWikiError),
     Io(std::io::Error),
+    /// The server responded with an unexpected HTTP status.
+    HttpStatus {
+        tool: String,
+        url: String,
+        status: u16,
+        body_excerpt: String,
+    },
+    /// The tool responded, but reported a failure.
+    ToolStatus {
+        tool: String,
+        status: String,
+    },
+    /// The response did not have the expected structure.
+    Parse {
+        tool: String,
+        field: String,
+        reason: String,
+    },
+    /// The request timed out.
+    Timeout {
+        url: String,
+        source: reqwest::Error,
+    },
+    /// The server responded with HTTP 429, even after retries.
+    RateLimited {
+        url: String,
+        retry_after: Option<Duration>,
+    },
+}
+
+impl ToolsError {
+    /// Creates a `Parse` error.
+    pub fn parse<S1: Into<String>, S2: Into<String>, S3: Into<String>>(
+        tool: S1,
+        field: S2,
+        reason: S3,
+    ) -> Self {
+        Self::Parse {
+            tool: tool.into(),
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Creates a `ToolStatus` error.
+    pub fn tool_status<S1: Into<String>, S2: Into<String>>(tool: S1, status: S2) -> Self {
+        Self::ToolStatus {
+            tool: tool.into(),
+            status: status.into(),
+        }
+    }
+
+    /// Creates an `HttpStatus` error, keeping only the start of the response body.
+    pub fn http_status<S1: Into<String>, S2: Into<String>>(
+        tool: S1,
+        url: S2,
+        status: u16,
+        body: &str,
+    ) -> Self {
+        Self::HttpStatus {
+            tool: tool.into(),
+            url: url.into(),
+            status,
+            body_excerpt: body.chars().take(BODY_EXCERPT_MAX_CHARS).collect(),
+        }
+    }
+
+    /// Returns the HTTP status code, if the error was caused by an HTTP response.
+    pub fn http_status_code(&self) -> Option<u16> {
+        match self {
+            Self::HttpStatus { status, .. } => Some(*status),
+            Self::RateLimited { .. } => Some(429),
+            Self::Reqwest(e) => e.status().map(|status| status.as_u16()),
+            _ => None,
+        }
+    }
 }
 
 impl Display for ToolsError {
@@ -28,15 +113,56 @@ impl Display for ToolsError {
             ToolsError::SerdeJson(e) => write!(f, "Serde JSON error: {}", e),
             ToolsError::MediaWiki(e) => write!(f, "MediaWiki error: {}", e),
             ToolsError::Io(e) => write!(f, "IO error: {}", e),
+            ToolsError::HttpStatus {
+                tool,
+                url,
+                status,
+                body_excerpt,
+            } => write!(f, "{tool}: HTTP status {status} for {url}: {body_excerpt}"),
+            ToolsError::ToolStatus { tool, status } => {
+                write!(f, "{tool}: status is not OK: {status}")
+            }
+            ToolsError::Parse {
+                tool,
+                field,
+                reason,
+            } => write!(f, "{tool}: can not parse {field}: {reason}"),
+            ToolsError::Timeout { url, .. } => write!(f, "Timeout for {url}"),
+            ToolsError::RateLimited { url, retry_after } => match retry_after {
+                Some(retry_after) => write!(
+                    f,
+                    "Rate limited for {url}, retry after {}s",
+                    retry_after.as_secs()
+                ),
+                None => write!(f, "Rate limited for {url}"),
+            },
         }
     }
 }
 
-impl Error for ToolsError {}
+impl Error for ToolsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ToolsError::Reqwest(e) => Some(e),
+            ToolsError::Csv(e) => Some(e),
+            ToolsError::SerdeJson(e) => Some(e),
+            ToolsError::MediaWiki(e) => Some(e),
+            ToolsError::Io(e) => Some(e),
+            ToolsError::Timeout { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 
 impl From<reqwest::Error> for ToolsError {
     fn from(e: reqwest::Error) -> Self {
-        Self::Reqwest(e)
+        match e.is_timeout() {
+            true => Self::Timeout {
+                url: e.url().map(|url| url.to_string()).unwrap_or_default(),
+                source: e,
+            },
+            false => Self::Reqwest(e),
+        }
     }
 }
 
@@ -63,3 +189,25 @@ impl From<std::io::Error> for ToolsError {
         Self::Io(e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kinds() {
+        let e = ToolsError::http_status("PetScan", "https://example.org", 500, &"x".repeat(1000));
+        assert_eq!(e.http_status_code(), Some(500));
+        match &e {
+            ToolsError::HttpStatus { body_excerpt, .. } => assert_eq!(body_excerpt.len(), 500),
+            _ => panic!("Expected HttpStatus"),
+        }
+        assert!(e.source().is_none());
+
+        let e: ToolsError = serde_json::from_str::<u8>("x").unwrap_err().into();
+        assert!(e.source().is_some());
+
+        let e = ToolsError::parse("Quarry", "headers", "not an array");
+        assert_eq!(e.to_string(), "Quarry: can not parse headers: not an array");
+    }
+}
@@ -1,4 +1,5 @@
-use mediawiki::{api::Api, title::Title};
+use crate::namespaces::NamespaceTable;
+use mediawiki::title::Title;
 use serde_json::json;
 
 #[derive(Debug, PartialEq)]
@@ -8,18 +9,18 @@ pub struct FancyTitle {
 }
 
 impl FancyTitle {
-    pub fn new(s: &str, ns: i64, api: &Api) -> Self {
+    pub fn new(s: &str, ns: i64, namespaces: &NamespaceTable) -> Self {
         let title = Title::new(s, ns);
         Self {
-            prefixed_title: title.full_pretty(api).unwrap_or_default(),
+            prefixed_title: namespaces.prefixed_title(&title).unwrap_or_default(),
             title,
         }
     }
 
-    pub fn from_prefixed(s: &str, api: &Api) -> Self {
-        let title = Title::new_from_full(s, api);
+    pub fn from_prefixed(s: &str, namespaces: &NamespaceTable) -> Self {
+        let title = namespaces.parse_prefixed(s);
         Self {
-            prefixed_title: title.full_pretty(api).unwrap_or_default(),
+            prefixed_title: namespaces.prefixed_title(&title).unwrap_or_default(),
             title,
         }
     }
@@ -16,13 +16,19 @@
 ///        println!("Page {} Description {} Lat {} Lon {} Image {}", result.title, result.description, result.lat, result.lon, result.image);
 ///     });
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, Site, Tool, ToolRequest, ToolResponse, ToolsError,
+    ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
 use lazy_static::lazy_static;
+use mediawiki::title::Title;
 use regex::Regex;
-use serde_json::{Value, json};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Grep {
     site: Site,
     pattern: String,
@@ -67,26 +73,27 @@ impl Grep {
     pub fn query(&self) -> &str {
         &self.pattern
     }
-
-    pub async fn as_json(&self) -> Value {
-        let namespace_id = self.namespace_id as i64;
-        let site = self.site();
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.results()
-                .iter()
-                .map(|result| FancyTitle::new(result, namespace_id, &api).to_json())
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
-    }
 }
 
 #[async_trait]
 impl Tool for Grep {
-    fn get_url(&self) -> String {
+    type Output = Vec<String>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        Ok(ToolRequest::get(self.get_url(ti)))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_text(&response.text())?;
+        Ok(self.results.clone())
+    }
+}
+
+impl Grep {
+    pub fn get_url(&self, ti: &ToolsInterface) -> String {
         let mut url = format!(
-            "https://grep.toolforge.org/index.php?lang={lang}&project={project}&namespace={namespace_id}&pattern={pattern}",
+            "{base}/index.php?lang={lang}&project={project}&namespace={namespace_id}&pattern={pattern}",
+            base = ti.endpoint(Endpoint::Grep),
             pattern = self.pattern,
             lang = self.site.language(),
             project = self.site.project(),
@@ -101,24 +108,6 @@ impl Tool for Grep {
         url
     }
 
-    #[cfg(feature = "blocking")]
-    /// Run the tool in a blocking manner.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        let url = self.get_url();
-        let client = crate::ToolsInterface::blocking_client()?;
-        let text = client.get(&url).send()?.text()?;
-        self.set_from_text(&text)
-    }
-
-    #[cfg(feature = "tokio")]
-    /// Run the tool asynchronously.
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = self.get_url();
-        let client = crate::ToolsInterface::tokio_client()?;
-        let text = client.get(&url).send().await?.text().await?;
-        self.set_from_text(&text)
-    }
-
     fn set_from_text(&mut self, text: &str) -> Result<(), ToolsError> {
         lazy_static! {
             static ref RE_PAGE: Regex = Regex::new(r#"<li><a href=".*?">(.+?)</a></li>"#)
@@ -133,9 +122,29 @@ impl Tool for Grep {
     }
 }
 
+impl PageListSource for Grep {
+    fn page_list_site(&self) -> Option<Site> {
+        Some(self.site.clone())
+    }
+
+    fn page_list_with(&self, _namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let pages = self
+            .results
+            .iter()
+            .map(|result| {
+                let title = Title::new(result, self.namespace_id as i64);
+                Page::new(title, PageMeta::default())
+            })
+            .collect();
+        Ok(PageList::new(self.site.clone(), pages))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::replay::Replay;
 
     #[test]
     fn test_new() {
@@ -146,12 +155,14 @@ mod tests {
         assert_eq!(tool.query(), query);
     }
 
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_json() {
         let site = Site::from_wiki("enwiki").unwrap();
         let query = "^Mag.*ske$";
+        let replay = Replay::start("grep_magnus_manske").await;
         let mut tool = Grep::new(site.clone(), query);
-        tool.run().await.unwrap();
+        tool.run_with(&replay).await.unwrap();
         assert!(
             tool.results()
                 .iter()
@@ -22,43 +22,75 @@
 //! If you would like to see other tools supported, add a request to the [Issue tracker](https://github.com/magnusmanske/tools_interface/issues).
 
 pub mod a_list_building_tool;
+pub mod cache;
 pub mod completer;
 pub mod duplicity;
+pub mod endpoint;
 pub mod error;
 pub mod fancy_title;
 pub mod grep;
 pub mod list_building;
 pub mod missing_topics;
+pub mod namespaces;
 pub mod page_list;
 pub mod pagepile;
 pub mod pageviews;
+pub mod paginated;
 pub mod persondata_template;
 pub mod petscan;
+pub mod petscan_output;
+pub mod petscan_query;
 pub mod quarry;
+pub mod query_spec;
 pub mod quickstatements;
+pub mod rate_limit;
+pub mod recorder;
+#[cfg(test)]
+mod replay;
+pub mod request;
+mod request_trace;
+pub mod retry;
 pub mod search;
 pub mod site;
+pub mod site_registry;
 pub mod sparql_rc;
 pub mod tool;
 pub mod tools_interface;
+pub mod transport;
 pub mod wiki_nearby;
 pub mod xtools_pages;
 
 pub use a_list_building_tool::AListBuildingTool;
+pub use cache::{FileCache, MemoryCache, ResponseCache};
 pub use completer::{Completer, CompleterFilter};
 pub use duplicity::Duplicity;
+pub use endpoint::Endpoint;
 pub use error::ToolsError;
 pub use missing_topics::MissingTopics;
+pub use namespaces::{Namespace, NamespaceTable};
+pub use page_list::{
+    MetaChange, Page, PageChange, PageList, PageListDiff, PageListSource, PageMeta,
+};
 pub use pagepile::PagePile;
 pub use pageviews::*;
+pub use paginated::{Paginated, ResultPage};
 pub use persondata_template::*;
 pub use petscan::*;
+pub use petscan_output::{PetScanExport, PetScanFormat, PetScanOutput, PetScanTable};
+pub use petscan_query::{PetScanCombination, PetScanQuery, PetScanSort, PetScanWikidata};
 pub use quarry::Quarry;
+pub use query_spec::{Provenance, QuerySpec};
 pub use quickstatements::QuickStatements;
+pub use rate_limit::RateLimit;
+pub use recorder::{Exchange, Fixture, Recorder};
+pub use request::{ToolRequest, ToolResponse};
+pub use retry::RetryPolicy;
 pub use site::Site;
+pub use site_registry::SiteRegistry;
 pub use sparql_rc::{EntityEdit, EntityEditor, SparqlRC};
 pub use tool::Tool;
 pub use tools_interface::ToolsInterface;
+pub use transport::{ReqwestTransport, Transport};
 
 /*
 TEST:
@@ -15,18 +15,24 @@
 ///        println!("Page {} Item {} Description {}", result.title, result.qid, result.description);
 ///     });
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, Site, Tool, ToolRequest, ToolResponse, ToolsError,
+    ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
-use serde_json::{Value, json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct ListBuildingResult {
     pub title: String,
     pub qid: String,
     pub description: String,
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ListBuilding {
     site: Site,
     title: String,
@@ -53,38 +59,37 @@ impl ListBuilding {
     pub fn title(&self) -> &str {
         &self.title
     }
-    pub async fn as_json(&self) -> Value {
-        let site = self.site();
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.results()
-                .iter()
-                .map(|result| (FancyTitle::from_prefixed(&result.title, &api).to_json(),result))
-                .map(|(mut v,result)| {
-                    v["wikidata"] = json!(result.qid);
-                    v["description"] = json!(result.description);
-                    v
-                })
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
-    }
 }
 
 #[async_trait]
 impl Tool for ListBuilding {
-    fn get_url(&self) -> String {
+    type Output = Vec<ListBuildingResult>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        Ok(ToolRequest::get(self.get_url(ti)))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.results.clone())
+    }
+}
+
+impl ListBuilding {
+    pub fn get_url(&self, ti: &ToolsInterface) -> String {
         format!(
-            "https://list-building.toolforge.org/api/serpentine?lang={lang}&title={title}&qid=&k-reader=3&k-links=3&k-morelike=4&wp",
+            "{base}/api/serpentine?lang={lang}&title={title}&qid=&k-reader=3&k-links=3&k-morelike=4&wp",
+            base = ti.endpoint(Endpoint::ListBuilding),
             lang = self.site.language(),
             title = self.title,
         )
     }
 
     fn set_from_json(&mut self, j: Value) -> Result<(), ToolsError> {
+        self.results.clear();
         for entry in j["results"]
             .as_array()
-            .ok_or_else(|| ToolsError::Json("Result is not an array".to_string()))?
+            .ok_or_else(|| ToolsError::parse("ListBuilding", "results", "not an array"))?
         {
             let title = match entry.get("page_title") {
                 Some(title) => match title.as_str() {
@@ -118,9 +123,33 @@ impl Tool for ListBuilding {
     }
 }
 
+impl PageListSource for ListBuilding {
+    fn page_list_site(&self) -> Option<Site> {
+        Some(self.site.clone())
+    }
+
+    fn page_list_with(&self, namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let pages = self
+            .results
+            .iter()
+            .map(|result| {
+                let meta = PageMeta {
+                    wikidata: Some(result.qid.to_owned()),
+                    description: Some(result.description.to_owned()),
+                    ..Default::default()
+                };
+                Page::from_prefixed(&result.title, namespaces, meta)
+            })
+            .collect();
+        Ok(PageList::new(self.site.clone(), pages))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::replay::Replay;
 
     #[test]
     fn test_new() {
@@ -131,12 +160,15 @@ mod tests {
         assert_eq!(tool.title(), title);
     }
 
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_list_building_json() {
         let site = Site::from_wiki("enwiki").unwrap();
         let title = "SARS-CoV-2";
+        let replay = Replay::start("list_building_sars_cov_2").await;
         let mut tool = ListBuilding::new(site, title);
-        tool.run().await.unwrap();
+        tool.run_with(&replay).await.unwrap();
+        assert_eq!(tool.results().len(), 3);
         assert!(
             tool.results()
                 .iter()
@@ -15,11 +15,17 @@
 ///        println!("{title} wanted {count} times");
 ///     });
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, Site, Tool, ToolRequest, ToolResponse, ToolsError,
+    ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
-use serde_json::{Value, json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MissingTopics {
     site: Site,
     category_depth: Option<u32>,
@@ -31,7 +37,6 @@ pub struct MissingTopics {
 
     url_used: String,
     results: Vec<(String, u64)>,
-    tool_url: String,
 }
 
 impl MissingTopics {
@@ -39,7 +44,6 @@ impl MissingTopics {
     pub fn new(site: Site) -> Self {
         Self {
             site,
-            tool_url: "https://missingtopics.toolforge.org/".to_string(),
             ..Default::default()
         }
     }
@@ -88,24 +92,26 @@ impl MissingTopics {
     pub fn site(&self) -> &Site {
         &self.site
     }
-
-    pub async fn as_json(&self) -> Value {
-        let site = self.site();
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.results()
-                .iter()
-                .map(|(prefixed_title,counter)| (FancyTitle::from_prefixed(prefixed_title, &api).to_json(),counter))
-                .map(|(mut v,counter)| {v["counter"] = json!(*counter); v})
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
-    }
 }
 
 #[async_trait]
 impl Tool for MissingTopics {
-    fn generate_paramters(&self) -> Result<Vec<(String, String)>, ToolsError> {
+    type Output = Vec<(String, u64)>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        let url = format!("{}/", ti.endpoint(Endpoint::MissingTopics));
+        let parameters = self.generate_paramters()?;
+        Ok(ToolRequest::get(url).query(&parameters))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.results.clone())
+    }
+}
+
+impl MissingTopics {
+    pub fn generate_paramters(&self) -> Result<Vec<(String, String)>, ToolsError> {
         let mut parameters: Vec<(String, String)> = [
             ("language".to_string(), self.site.language().to_string()),
             ("project".to_string(), self.site.project().to_string()),
@@ -144,83 +150,62 @@ impl Tool for MissingTopics {
         Ok(parameters)
     }
 
-    #[cfg(feature = "tokio")]
-    /// Run the query asynchronously.
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = &self.tool_url;
-        let parameters = self.generate_paramters()?;
-        let client = crate::ToolsInterface::tokio_client()?;
-        let response = client.get(url).query(&parameters).send().await?;
-        let j: Value = response.json().await?;
-        self.set_from_json(j)
-    }
-
-    #[cfg(feature = "blocking")]
-    /// Run the query in a blocking manner.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        let url = &self.tool_url;
-        let parameters = self.generate_paramters()?;
-        let client = crate::ToolsInterface::blocking_client()?;
-        let j: Value = client.get(url).query(&parameters).send()?.json()?;
-        self.set_from_json(j)
-    }
-
     fn set_from_json(&mut self, j: Value) -> Result<(), ToolsError> {
         if j["status"].as_str() != Some("OK") {
-            return Err(ToolsError::Tool(format!(
-                "MissingTopics status is not OK: {:?}",
-                j["status"]
-            )));
+            return Err(ToolsError::tool_status(
+                "MissingTopics",
+                j["status"].to_string(),
+            ));
         }
         self.results = j["results"]
             .as_object()
-            .ok_or(ToolsError::Json("['results'] has no object".into()))?
+            .ok_or_else(|| ToolsError::parse("MissingTopics", "results", "not an object"))?
             .iter()
             .filter_map(|(k, v)| Some((k.to_string(), v.as_u64()?)))
             .collect();
         self.url_used = j["url"]
             .as_str()
-            .ok_or(ToolsError::Json("['url'] is missing".into()))?
+            .ok_or_else(|| ToolsError::parse("MissingTopics", "url", "missing"))?
             .to_string();
         Ok(())
     }
 }
 
-#[cfg(test)]
+impl PageListSource for MissingTopics {
+    fn page_list_site(&self) -> Option<Site> {
+        Some(self.site.clone())
+    }
+
+    fn page_list_with(&self, namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let pages = self
+            .results
+            .iter()
+            .map(|(prefixed_title, counter)| {
+                let meta = PageMeta {
+                    counter: Some(*counter),
+                    ..Default::default()
+                };
+                Page::from_prefixed(prefixed_title, namespaces, meta)
+            })
+            .collect();
+        Ok(PageList::new(self.site.clone(), pages))
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
 mod tests {
     use super::*;
     use crate::Site;
-    use serde_json::json;
-    use wiremock::matchers::{method, path, query_param_contains};
-    use wiremock::{Mock, MockServer, ResponseTemplate};
-
-    async fn get_mock_server() -> MockServer {
-        let mock_path = "/";
-        let mock_server = MockServer::start().await;
-        Mock::given(method("GET"))
-            .and(query_param_contains("language","de"))
-            .and(query_param_contains("project","wikipedia"))
-            .and(query_param_contains("article","Biologie"))
-            .and(query_param_contains("language","de"))
-            .and(query_param_contains("doit","Run"))
-            .and(query_param_contains("wikimode","json"))
-            .and(query_param_contains("no_template_links","1"))
-            .and(path(mock_path))
-            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"results":{"Ethnobiologie":4,"Landschaftsdiversität":3,"Micrographia":4,"Spezielle_Botanik":6,"Wbetavirus":4,"Zellphysiologie":4},"status":"OK","url":"https://missingtopics.toolforge.org/?language=de&project=wikipedia&depth=1&category=&article=Biologie&wikimode=json&limitnum=1&notemplatelinks=0"})))
-            .mount(&mock_server)
-            .await;
-        mock_server
-    }
+    use crate::replay::Replay;
 
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_missing_topics_run_async() {
-        let mock_server = get_mock_server().await;
+        let replay = Replay::start("missing_topics_biologie").await;
         let mut mt = MissingTopics::new(Site::from_wiki("dewiki").unwrap())
             .with_article("Biologie")
             .no_template_links(true);
-        mt.tool_url = format!("{}/", mock_server.uri());
-        mt.run().await.unwrap();
+        mt.run_with(&replay).await.unwrap();
         assert_eq!(mt.results.len(), 6);
         assert_eq!(mt.results[5].0, "Zellphysiologie");
         assert_eq!(mt.results[5].1, 4);
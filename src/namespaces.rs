@@ -0,0 +1,345 @@
+/// # Namespaces
+/// `NamespaceTable` maps namespace IDs to names and back, for one wiki.
+/// It is used to format and parse namespace-prefixed titles, without a live `siteinfo` call for each conversion.
+/// A table can come from a snapshot bundled with this crate (`bundled`), a `siteinfo` JSON file (`from_file`),
+/// or a live MediaWiki API (`from_api`). `for_site` (and `for_site_blocking`, and their `_with` variants) try these in turn,
+/// and fall back to the canonical MediaWiki names.
+///
+/// ## Example
+/// ```ignore
+/// let namespaces = NamespaceTable::bundled("dewiki").unwrap();
+/// let title = namespaces.parse_prefixed("Benutzer:Magnus Manske");
+/// assert_eq!(title.namespace_id(), 2);
+/// assert_eq!(namespaces.prefixed_title(&title).unwrap(), "Benutzer:Magnus Manske");
+/// ```
+use crate::{Site, ToolsError};
+#[cfg(any(feature = "tokio", feature = "blocking"))]
+use crate::{ToolRequest, ToolsInterface};
+use lazy_static::lazy_static;
+use mediawiki::api::Api;
+use mediawiki::title::Title;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufReader;
+
+/// Namespace snapshot for some large wikis, in `siteinfo` format, keyed by wiki.
+const BUNDLED_NAMESPACES: &str = include_str!("../data/namespaces.json");
+
+/// Canonical names of the MediaWiki core namespaces.
+const CANONICAL_NAMESPACES: &[(i64, &str)] = &[
+    (-2, "Media"),
+    (-1, "Special"),
+    (0, ""),
+    (1, "Talk"),
+    (2, "User"),
+    (3, "User talk"),
+    (4, "Project"),
+    (5, "Project talk"),
+    (6, "File"),
+    (7, "File talk"),
+    (8, "MediaWiki"),
+    (9, "MediaWiki talk"),
+    (10, "Template"),
+    (11, "Template talk"),
+    (12, "Help"),
+    (13, "Help talk"),
+    (14, "Category"),
+    (15, "Category talk"),
+];
+
+lazy_static! {
+    static ref BUNDLED: HashMap<String, NamespaceTable> = {
+        let json: Value = serde_json::from_str(BUNDLED_NAMESPACES)
+            .expect("Bundled namespaces should be valid JSON");
+        json.as_object()
+            .expect("Bundled namespaces should be an object")
+            .iter()
+            .map(|(wiki, siteinfo)| {
+                let table = NamespaceTable::from_json(siteinfo)
+                    .expect("Bundled namespaces should be valid siteinfo");
+                (wiki.to_owned(), table)
+            })
+            .collect()
+    };
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Namespace {
+    pub id: i64,
+    /// The local name, e.g. "Benutzer" on dewiki.
+    pub name: String,
+    /// The canonical (English) name, e.g. "User".
+    pub canonical: Option<String>,
+    /// If `false`, the first letter of titles in this namespace is always uppercase.
+    pub case_sensitive: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NamespaceTable {
+    namespaces: BTreeMap<i64, Namespace>,
+    aliases: BTreeMap<String, i64>,
+}
+
+impl NamespaceTable {
+    /// Returns a table with the canonical names of the MediaWiki core namespaces.
+    /// Local names and extension namespaces are not known.
+    pub fn canonical() -> Self {
+        let mut ret = Self::default();
+        for (id, name) in CANONICAL_NAMESPACES {
+            ret.insert(Namespace {
+                id: *id,
+                name: name.to_string(),
+                canonical: Some(name.to_string()).filter(|name| !name.is_empty()),
+                case_sensitive: false,
+            });
+        }
+        ret
+    }
+
+    /// Returns the table bundled with this crate for a wiki, if any.
+    pub fn bundled(wiki: &str) -> Option<Self> {
+        BUNDLED.get(wiki).cloned()
+    }
+
+    /// Reads a table from `siteinfo` JSON, with `namespaces` and (optionally) `namespacealiases`.
+    /// The JSON can be the complete API response, or its `query` part.
+    pub fn from_json(json: &Value) -> Result<Self, ToolsError> {
+        let json = match json.get("query") {
+            Some(query) => query,
+            None => json,
+        };
+        let mut ret = Self::default();
+        for ns in json["namespaces"]
+            .as_object()
+            .ok_or_else(|| ToolsError::parse("NamespaceTable", "namespaces", "not an object"))?
+            .values()
+        {
+            let id = ns["id"]
+                .as_i64()
+                .ok_or_else(|| ToolsError::parse("NamespaceTable", "namespaces.id", "missing"))?;
+            let name = ns["*"]
+                .as_str()
+                .ok_or_else(|| ToolsError::parse("NamespaceTable", "namespaces.*", "missing"))?;
+            ret.insert(Namespace {
+                id,
+                name: Title::underscores_to_spaces(name),
+                canonical: ns["canonical"].as_str().map(Title::underscores_to_spaces),
+                case_sensitive: ns["case"].as_str() == Some("case-sensitive"),
+            });
+        }
+        for alias in json["namespacealiases"].as_array().into_iter().flatten() {
+            if let (Some(id), Some(name)) = (alias["id"].as_i64(), alias["*"].as_str()) {
+                ret.aliases.insert(Title::underscores_to_spaces(name), id);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Reads a table from a `siteinfo` JSON file.
+    pub fn from_file(filename: &str) -> Result<Self, ToolsError> {
+        let file = File::open(filename)?;
+        let reader = BufReader::new(file);
+        let json: Value = serde_json::from_reader(reader)?;
+        Self::from_json(&json)
+    }
+
+    /// Takes the table from the site info of a MediaWiki API.
+    pub fn from_api(api: &Api) -> Result<Self, ToolsError> {
+        Self::from_json(api.get_site_info())
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Returns the table for a site: the bundled one if available, otherwise from the live API.
+    /// If the API can not be reached, the canonical table is returned.
+    pub async fn for_site(site: &Site) -> Self {
+        Self::for_site_with(&ToolsInterface::global(), site).await
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Same as `for_site`, querying the live API with the given `ToolsInterface`.
+    pub async fn for_site_with(ti: &ToolsInterface, site: &Site) -> Self {
+        if let Some(table) = Self::bundled(site.wiki()) {
+            return table;
+        }
+        ti.execute_checked(&Self::siteinfo_request(site))
+            .await
+            .and_then(|response| Self::from_json(&response.json()?))
+            .unwrap_or_else(|_| Self::canonical())
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `for_site`, but queries the live API with a blocking request.
+    pub fn for_site_blocking(site: &Site) -> Self {
+        Self::for_site_blocking_with(&ToolsInterface::global(), site)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `for_site_blocking`, querying the live API with the given `ToolsInterface`.
+    pub fn for_site_blocking_with(ti: &ToolsInterface, site: &Site) -> Self {
+        if let Some(table) = Self::bundled(site.wiki()) {
+            return table;
+        }
+        ti.execute_checked_blocking(&Self::siteinfo_request(site))
+            .and_then(|response| Self::from_json(&response.json()?))
+            .unwrap_or_else(|_| Self::canonical())
+    }
+
+    #[cfg(any(feature = "tokio", feature = "blocking"))]
+    fn siteinfo_request(site: &Site) -> ToolRequest {
+        let parameters: Vec<(String, String)> = [
+            ("action", "query"),
+            ("meta", "siteinfo"),
+            ("siprop", "namespaces|namespacealiases"),
+            ("format", "json"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        ToolRequest::get(site.api_url()).query(&parameters)
+    }
+
+    /// Returns the table as `siteinfo` JSON, as read by `from_json`.
+    pub fn to_json(&self) -> Value {
+        let namespaces: serde_json::Map<String, Value> = self
+            .namespaces
+            .values()
+            .map(|ns| {
+                let mut j = json!({
+                    "id": ns.id,
+                    "*": ns.name,
+                    "case": if ns.case_sensitive { "case-sensitive" } else { "first-letter" },
+                });
+                if let Some(canonical) = &ns.canonical {
+                    j["canonical"] = json!(canonical);
+                }
+                (ns.id.to_string(), j)
+            })
+            .collect();
+        let aliases: Vec<Value> = self
+            .aliases
+            .iter()
+            .map(|(name, id)| json!({"id": id, "*": name}))
+            .collect();
+        json!({"namespaces": namespaces, "namespacealiases": aliases})
+    }
+
+    /// Returns the namespace with the given ID.
+    pub fn namespace(&self, id: i64) -> Option<&Namespace> {
+        self.namespaces.get(&id)
+    }
+
+    /// Returns the local name of the namespace with the given ID.
+    pub fn name(&self, id: i64) -> Option<&str> {
+        self.namespace(id).map(|ns| ns.name.as_str())
+    }
+
+    /// Returns the ID of a namespace by local name, canonical name, or alias.
+    /// Names are compared case-insensitively, with underscores treated as spaces.
+    pub fn id(&self, name: &str) -> Option<i64> {
+        let name = Self::normalize(name);
+        self.namespaces
+            .values()
+            .find(|ns| {
+                Self::normalize(&ns.name) == name
+                    || ns
+                        .canonical
+                        .as_deref()
+                        .is_some_and(|canonical| Self::normalize(canonical) == name)
+            })
+            .map(|ns| ns.id)
+            .or_else(|| {
+                self.aliases
+                    .iter()
+                    .find(|(alias, _)| Self::normalize(alias) == name)
+                    .map(|(_, id)| *id)
+            })
+    }
+
+    /// Returns the namespace-prefixed title, with spaces, e.g. "User talk:Foo".
+    /// Returns `None` if the namespace is not known.
+    pub fn prefixed_title(&self, title: &Title) -> Option<String> {
+        match self.name(title.namespace_id())? {
+            "" => Some(title.pretty().to_string()),
+            ns => Some(format!("{ns}:{}", title.pretty())),
+        }
+    }
+
+    /// Parses a namespace-prefixed title, e.g. "User talk:Foo".
+    /// If the prefix is not a known namespace, the whole string is a title in the main namespace.
+    pub fn parse_prefixed(&self, prefixed_title: &str) -> Title {
+        if let Some((prefix, title)) = prefixed_title.split_once(':')
+            && let Some(id) = self.id(prefix)
+        {
+            let title = Title::underscores_to_spaces(title);
+            let title = match self.namespace(id) {
+                Some(ns) if ns.case_sensitive => title,
+                _ => Title::first_letter_uppercase(&title),
+            };
+            return Title::new(&title, id);
+        }
+        Title::new(prefixed_title, 0)
+    }
+
+    fn insert(&mut self, namespace: Namespace) {
+        self.namespaces.insert(namespace.id, namespace);
+    }
+
+    fn normalize(name: &str) -> String {
+        Title::underscores_to_spaces(name).to_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled() {
+        let namespaces = NamespaceTable::bundled("dewiki").unwrap();
+        let title = namespaces.parse_prefixed("benutzer_Diskussion:magnus_Manske");
+        assert_eq!(title, Title::new("Magnus Manske", 3));
+        assert_eq!(
+            namespaces.prefixed_title(&title).unwrap(),
+            "Benutzer Diskussion:Magnus Manske"
+        );
+        assert_eq!(namespaces.id("User talk"), Some(3));
+        assert_eq!(namespaces.id("Bild"), Some(6));
+        assert_eq!(
+            namespaces.parse_prefixed("Not a namespace:Foo"),
+            Title::new("Not a namespace:Foo", 0)
+        );
+        assert!(NamespaceTable::bundled("nosuchwiki").is_none());
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let namespaces = NamespaceTable::bundled("enwiki").unwrap();
+        let json = json!({"query": namespaces.to_json()});
+        assert_eq!(NamespaceTable::from_json(&json).unwrap(), namespaces);
+        assert_eq!(NamespaceTable::canonical().name(4), Some("Project"));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_for_site_with() {
+        use wiremock::matchers::{path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = json!({"query":{"namespaces":{"0":{"id":0,"*":""},"2":{"id":2,"*":"Benutzer","canonical":"User"}}}});
+        Mock::given(path("/w/api.php"))
+            .and(query_param("meta", "siteinfo"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .host_override("als.wikipedia.org", server.uri())
+            .build()
+            .unwrap();
+        let site = Site::from_wiki("alswiki").unwrap();
+        let namespaces = NamespaceTable::for_site_with(&ti, &site).await;
+        assert_eq!(namespaces.name(2), Some("Benutzer"));
+    }
+}
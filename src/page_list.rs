@@ -1,24 +1,172 @@
+/// # Page list
+/// `PageList` is a list of pages on one site, with metadata per page.
+/// It can be combined with other lists (`union`, `subset`), converted to another wiki, and exported as JSON.
+/// Every tool that returns pages implements `PageListSource`, so its results can be turned into a `PageList` directly.
+/// Namespace prefixes are handled by a `NamespaceTable`, so titles can be formatted and parsed offline.
+/// Lists produced by a `QuerySpec` carry their `Provenance`, which is exported as `meta`.
+/// Two runs of the same query can be compared with `diff`, which returns a `PageListDiff`.
+///
+/// ## Example
+/// ```ignore
+/// let mut ps = PetScan::new(12345);
+/// ps.run().await?;
+/// let mut pp = PagePile::new(67890);
+/// pp.run().await?;
+/// let both = ps.page_list().await?.subset(&pp.page_list().await?).await;
+/// ```
 use crate::fancy_title::FancyTitle;
-use crate::{Site, ToolsError, ToolsInterface};
-use mediawiki::api::Api;
+use crate::{Endpoint, NamespaceTable, Provenance, Site, ToolRequest, ToolsError, ToolsInterface};
+use async_trait::async_trait;
 use mediawiki::title::Title;
 use serde_json::{self, Map, Value, json};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::io::BufReader;
 
+/// Metadata for a page. Values without a typed field are kept in `extra`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageMeta {
+    pub wikidata: Option<String>,
+    pub description: Option<String>,
+    pub counter: Option<u64>,
+    pub page_id: Option<u64>,
+    pub size: Option<u64>,
+    pub timestamp: Option<String>,
+    pub image: Option<String>,
+    pub coordinates: Option<(f64, f64)>,
+    pub distance: Option<f64>,
+    pub extra: Map<String, Value>,
+}
+
+impl PageMeta {
+    /// Takes the typed fields out of a JSON object; all other keys go into `extra`.
+    /// Values that do not fit their typed field, e.g. a `page_id` that is not a number, are kept in `extra` as well.
+    pub fn from_map(mut map: Map<String, Value>) -> Self {
+        fn take<T>(
+            map: &mut Map<String, Value>,
+            key: &str,
+            f: fn(&Value) -> Option<T>,
+        ) -> Option<T> {
+            let ret = f(map.get(key)?)?;
+            map.remove(key);
+            Some(ret)
+        }
+        let string = |v: &Value| v.as_str().map(|s| s.to_string());
+        let wikidata = take(&mut map, "wikidata", string);
+        let description = take(&mut map, "description", string);
+        let timestamp = take(&mut map, "timestamp", string);
+        let image = take(&mut map, "image", string);
+        let counter = take(&mut map, "counter", Value::as_u64);
+        let page_id = take(&mut map, "page_id", Value::as_u64);
+        let size = take(&mut map, "size", Value::as_u64);
+        let distance = take(&mut map, "distance", Value::as_f64);
+        // Coordinates are only taken as a pair
+        let coordinates = map
+            .get("lat")
+            .and_then(Value::as_f64)
+            .zip(map.get("lon").and_then(Value::as_f64));
+        if coordinates.is_some() {
+            map.remove("lat");
+            map.remove("lon");
+        }
+        Self {
+            wikidata,
+            description,
+            counter,
+            page_id,
+            size,
+            timestamp,
+            image,
+            coordinates,
+            distance,
+            extra: map,
+        }
+    }
+
+    /// Returns all metadata as a JSON object.
+    pub fn to_map(&self) -> Map<String, Value> {
+        let mut map = self.extra.clone();
+        let mut set = |key: &str, value: Option<Value>| {
+            if let Some(value) = value {
+                map.insert(key.to_string(), value);
+            }
+        };
+        set("wikidata", self.wikidata.as_ref().map(|v| json!(v)));
+        set("description", self.description.as_ref().map(|v| json!(v)));
+        set("counter", self.counter.map(|v| json!(v)));
+        set("page_id", self.page_id.map(|v| json!(v)));
+        set("size", self.size.map(|v| json!(v)));
+        set("timestamp", self.timestamp.as_ref().map(|v| json!(v)));
+        set("image", self.image.as_ref().map(|v| json!(v)));
+        set("lat", self.coordinates.map(|(lat, _)| json!(lat)));
+        set("lon", self.coordinates.map(|(_, lon)| json!(lon)));
+        set("distance", self.distance.map(|v| json!(v)));
+        map
+    }
+
+    /// Sets a value in `extra`.
+    pub fn with_extra<S: Into<String>>(mut self, key: S, value: Value) -> Self {
+        self.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Adds the values of `other`; values present in both are taken from `other`.
+    fn merge(&mut self, other: &PageMeta) {
+        fn merge_option<T: Clone>(a: &mut Option<T>, b: &Option<T>) {
+            if b.is_some() {
+                *a = b.clone();
+            }
+        }
+        merge_option(&mut self.wikidata, &other.wikidata);
+        merge_option(&mut self.description, &other.description);
+        merge_option(&mut self.counter, &other.counter);
+        merge_option(&mut self.page_id, &other.page_id);
+        merge_option(&mut self.size, &other.size);
+        merge_option(&mut self.timestamp, &other.timestamp);
+        merge_option(&mut self.image, &other.image);
+        merge_option(&mut self.coordinates, &other.coordinates);
+        merge_option(&mut self.distance, &other.distance);
+        self.extra.extend(other.extra.clone());
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Page {
     title: Title,
-    meta: Map<String, Value>,
+    meta: PageMeta,
 }
 
 impl Page {
-    pub fn as_json(&self, api: &Api) -> Value {
+    pub fn new(title: Title, meta: PageMeta) -> Self {
+        Self { title, meta }
+    }
+
+    /// Creates a page from a namespace-prefixed title, e.g. "Talk:Foo".
+    pub fn from_prefixed(
+        prefixed_title: &str,
+        namespaces: &NamespaceTable,
+        meta: PageMeta,
+    ) -> Self {
+        Self::new(namespaces.parse_prefixed(prefixed_title), meta)
+    }
+
+    pub fn title(&self) -> &Title {
+        &self.title
+    }
+
+    pub fn meta(&self) -> &PageMeta {
+        &self.meta
+    }
+
+    pub fn meta_mut(&mut self) -> &mut PageMeta {
+        &mut self.meta
+    }
+
+    pub fn as_json(&self, namespaces: &NamespaceTable) -> Value {
         let mut json =
-            FancyTitle::new(self.title.pretty(), self.title.namespace_id(), api).to_json();
-        self.meta.iter().for_each(|(key, value)| {
-            json[key] = value.clone();
+            FancyTitle::new(self.title.pretty(), self.title.namespace_id(), namespaces).to_json();
+        self.meta.to_map().into_iter().for_each(|(key, value)| {
+            json[key] = value;
         });
         json
     }
@@ -33,7 +181,7 @@ impl Page {
 
     fn merge(&self, other: &Page) -> Page {
         let mut meta = self.meta.clone();
-        meta.extend(other.meta.clone());
+        meta.merge(&other.meta);
         Page {
             title: self.title.clone(),
             meta,
@@ -45,19 +193,34 @@ impl Page {
 pub struct PageList {
     pages: Vec<Page>,
     site: Site,
+    provenance: Option<Provenance>,
 }
 
 impl PageList {
+    pub fn new(site: Site, pages: Vec<Page>) -> Self {
+        Self {
+            pages,
+            site,
+            provenance: None,
+        }
+    }
+
+    /// Sets the provenance of the list.
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
     pub fn from_json(json: &Value) -> Result<Self, ToolsError> {
         let wiki = json["site"]["wiki"]
             .as_str()
-            .ok_or(ToolsError::Json("missing site.wiki".to_string()))?;
+            .ok_or_else(|| ToolsError::parse("PageList", "site.wiki", "missing"))?;
         let site = Site::from_wiki(wiki)
             .ok_or_else(|| ToolsError::Tool(format!("Unknown wiki {wiki}")))?;
 
         let pages = json["pages"]
             .as_array()
-            .ok_or(ToolsError::Json("missing pages".to_string()))?;
+            .ok_or_else(|| ToolsError::parse("PageList", "pages", "missing"))?;
         let pages = pages
             .iter()
             .filter_map(|page| {
@@ -68,10 +231,21 @@ impl PageList {
                 let _ = meta.remove("namespace_id")?;
                 let _ = meta.remove("prefixed_title")?;
                 let title = Title::new(title, namespace_id);
-                Some(Page { title, meta })
+                Some(Page::new(title, PageMeta::from_map(meta)))
             })
             .collect();
-        Ok(Self { pages, site })
+        let provenance = match json.get("meta") {
+            Some(Value::Null) | None => None,
+            Some(meta) => Some(
+                serde_json::from_value(meta.to_owned())
+                    .map_err(|e| ToolsError::parse("PageList", "meta", e.to_string()))?,
+            ),
+        };
+        Ok(Self {
+            pages,
+            site,
+            provenance,
+        })
     }
 
     pub fn from_file(filename: &str) -> Result<Self, ToolsError> {
@@ -89,63 +263,225 @@ impl PageList {
         &self.pages
     }
 
+    /// Returns where the list came from, if known.
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    pub fn push(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Returns the list as JSON, using the namespace table of the site (see `NamespaceTable::for_site`).
     pub async fn as_json(&self) -> Value {
-        let site = self.site();
-        let api = site.api().await.ok().unwrap();
-        json!({
+        let namespaces = NamespaceTable::for_site(self.site()).await;
+        self.as_json_with(&namespaces)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `as_json`, in a blocking manner.
+    pub fn as_json_blocking(&self) -> Value {
+        let namespaces = NamespaceTable::for_site_blocking(self.site());
+        self.as_json_with(&namespaces)
+    }
+
+    /// Returns the list as JSON, using the given namespace table for prefixed titles.
+    pub fn as_json_with(&self, namespaces: &NamespaceTable) -> Value {
+        let mut json = json!({
             "pages": self.pages()
                 .iter()
-                .map(|page| page.as_json(&api))
+                .map(|page| page.as_json(namespaces))
                 .collect::<Vec<Value>>(),
-            "site": site,
-        })
+            "site": self.site(),
+        });
+        if let Some(provenance) = &self.provenance {
+            json["meta"] = json!(provenance);
+        }
+        json
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn to_wiki(&self, target_wiki: &str) -> Result<Self, ToolsError> {
+        self.to_wiki_with(&ToolsInterface::global(), target_wiki)
+            .await
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Same as `to_wiki`, using the given `ToolsInterface`.
+    pub async fn to_wiki_with(
+        &self,
+        ti: &ToolsInterface,
+        target_wiki: &str,
+    ) -> Result<Self, ToolsError> {
+        let target_site = Self::target_site(target_wiki)?;
+        let namespaces = NamespaceTable::for_site_with(ti, &self.site).await;
+        let target_namespaces = NamespaceTable::for_site_with(ti, &target_site).await;
+        let request = self.change_wiki_request(ti, &target_site, &namespaces);
+        let response = ti.execute_checked(&request).await?;
+        let old2new: HashMap<String, String> = serde_json::from_value(response.json()?)?;
+        Ok(self.with_changed_titles(target_site, &namespaces, &target_namespaces, &old2new))
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `to_wiki`, in a blocking manner.
+    pub fn to_wiki_blocking(&self, target_wiki: &str) -> Result<Self, ToolsError> {
+        self.to_wiki_blocking_with(&ToolsInterface::global(), target_wiki)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `to_wiki_blocking`, using the given `ToolsInterface`.
+    pub fn to_wiki_blocking_with(
+        &self,
+        ti: &ToolsInterface,
+        target_wiki: &str,
+    ) -> Result<Self, ToolsError> {
+        let target_site = Self::target_site(target_wiki)?;
+        let namespaces = NamespaceTable::for_site_blocking_with(ti, &self.site);
+        let target_namespaces = NamespaceTable::for_site_blocking_with(ti, &target_site);
+        let request = self.change_wiki_request(ti, &target_site, &namespaces);
+        let response = ti.execute_checked_blocking(&request)?;
+        let old2new: HashMap<String, String> = serde_json::from_value(response.json()?)?;
+        Ok(self.with_changed_titles(target_site, &namespaces, &target_namespaces, &old2new))
+    }
+
+    #[cfg(any(feature = "tokio", feature = "blocking"))]
+    fn target_site(target_wiki: &str) -> Result<Site, ToolsError> {
+        Site::from_wiki(target_wiki)
+            .ok_or_else(|| ToolsError::Tool(format!("Unknown wiki {target_wiki}")))
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn subset(&self, other: &Self) -> Result<Self, ToolsError> {
+        // Convert to same wiki, if necessary
+        if self.site != other.site {
+            return Ok(self.subset_same_site(&other.to_wiki(self.site.wiki()).await?));
+        }
+        Ok(self.subset_same_site(other))
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `subset`, in a blocking manner.
+    pub fn subset_blocking(&self, other: &Self) -> Result<Self, ToolsError> {
+        // Convert to same wiki, if necessary
+        if self.site != other.site {
+            return Ok(self.subset_same_site(&other.to_wiki_blocking(self.site.wiki())?));
+        }
+        Ok(self.subset_same_site(other))
+    }
+
+    #[cfg(feature = "tokio")]
+    pub async fn union(&self, other: &Self) -> Result<Self, ToolsError> {
+        // Convert to same wiki, if necessary
+        if self.site != other.site {
+            return Ok(self.union_same_site(&other.to_wiki(self.site.wiki()).await?));
+        }
+        Ok(self.union_same_site(other))
     }
 
-    pub async fn to_wiki(&self, target_wiki: &str) -> Self {
-        let api = self.site.api().await.ok().unwrap();
-        let source_wiki = self.site.wiki();
-        let url =
-            format!("https://wd-infernal.toolforge.org/change_wiki/{source_wiki}/{target_wiki}");
+    #[cfg(feature = "blocking")]
+    /// Same as `union`, in a blocking manner.
+    pub fn union_blocking(&self, other: &Self) -> Result<Self, ToolsError> {
+        // Convert to same wiki, if necessary
+        if self.site != other.site {
+            return Ok(self.union_same_site(&other.to_wiki_blocking(self.site.wiki())?));
+        }
+        Ok(self.union_same_site(other))
+    }
+
+    /// Compares this list with a `newer` one for the same site.
+    /// Returns the pages that were added, removed, or whose metadata changed.
+    pub fn diff(&self, newer: &Self) -> Result<PageListDiff, ToolsError> {
+        if self.site != newer.site {
+            return Err(ToolsError::Tool(format!(
+                "Can not diff page lists for different sites ({} and {})",
+                self.site.wiki(),
+                newer.site.wiki()
+            )));
+        }
+        let old_pages = self
+            .pages
+            .iter()
+            .map(|page| (page.key(), page))
+            .collect::<HashMap<String, &Page>>();
+        let new_keys = newer
+            .pages
+            .iter()
+            .map(|page| page.key())
+            .collect::<HashSet<String>>();
+        let mut diff = PageListDiff {
+            site: self.site.clone(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+        for page in &newer.pages {
+            match old_pages.get(&page.key()) {
+                Some(old_page) => {
+                    let changes = MetaChange::between(&old_page.meta, &page.meta);
+                    if !changes.is_empty() {
+                        diff.changed.push(PageChange {
+                            page: page.clone(),
+                            changes,
+                        });
+                    }
+                }
+                None => diff.added.push(page.clone()),
+            }
+        }
+        diff.removed = self
+            .pages
+            .iter()
+            .filter(|page| !new_keys.contains(&page.key()))
+            .cloned()
+            .collect();
+        Ok(diff)
+    }
+
+    fn change_wiki_request(
+        &self,
+        ti: &ToolsInterface,
+        target_site: &Site,
+        namespaces: &NamespaceTable,
+    ) -> ToolRequest {
+        let url = format!(
+            "{base}/change_wiki/{source_wiki}/{target_wiki}",
+            base = ti.endpoint(Endpoint::WdInfernal),
+            source_wiki = self.site.wiki(),
+            target_wiki = target_site.wiki(),
+        );
         let pages: Vec<String> = self
             .pages
             .iter()
-            .filter_map(|page| page.title.full_pretty(&api))
+            .filter_map(|page| namespaces.prefixed_title(&page.title))
             .collect();
-        let payload = json!(pages);
-        let client = ToolsInterface::tokio_client().unwrap();
-        let old2new: HashMap<String, String> = client
-            .post(url) // Replace with your URL
-            .json(&payload) // Set the JSON payload
-            .send() // Send the request
-            .await // Await the response
-            .unwrap()
-            .json()
-            .await
-            .unwrap();
+        ToolRequest::post(url).json(json!(pages))
+    }
 
+    fn with_changed_titles(
+        &self,
+        target_site: Site,
+        namespaces: &NamespaceTable,
+        target_namespaces: &NamespaceTable,
+        old2new: &HashMap<String, String>,
+    ) -> Self {
         let mut ret = Self {
-            site: Site::from_wiki(target_wiki).unwrap(),
+            site: target_site,
             pages: Vec::new(),
+            provenance: self.provenance.clone(),
         };
         for page in &self.pages {
-            let title = page.title.full_pretty(&api).unwrap();
+            let title = namespaces.prefixed_title(&page.title).unwrap_or_default();
             if let Some(new_title) = old2new.get(&title) {
                 let mut new_page = page.clone();
-                new_page.title = Title::new_from_full(new_title, &api);
+                new_page.title = target_namespaces.parse_prefixed(new_title);
                 ret.pages.push(new_page);
             }
         }
-
         ret
     }
 
-    pub async fn subset(&self, other: &Self) -> Self {
-        let mut other = other.to_owned();
-        // Convert to same wiki, if necessary
-        if self.site != other.site {
-            other = other.to_wiki(self.site.wiki()).await;
-        }
-
+    fn subset_same_site(&self, other: &Self) -> Self {
         let title2pos = other
             .pages
             .iter()
@@ -158,19 +494,10 @@ impl PageList {
             .filter(|page| title2pos.contains_key(&page.key()))
             .map(|page| page.merge(&other.pages[title2pos[&page.key()]]))
             .collect();
-        Self {
-            pages,
-            site: self.site.clone(),
-        }
+        Self::new(self.site.clone(), pages)
     }
 
-    pub async fn union(&self, other: &Self) -> Self {
-        let mut other = other.to_owned();
-        // Convert to same wiki, if necessary
-        if self.site != other.site {
-            other = other.to_wiki(self.site.wiki()).await;
-        }
-
+    fn union_same_site(&self, other: &Self) -> Self {
         // Get unique and merged pages from this set
         let mut title2pos = other
             .pages
@@ -194,51 +521,355 @@ impl PageList {
             .collect::<Vec<_>>();
         pages.extend(other_pages);
 
-        Self {
-            pages,
-            site: self.site.clone(),
-        }
+        Self::new(self.site.clone(), pages)
+    }
+}
+
+/// The change of one metadata value between two versions of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaChange {
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl MetaChange {
+    /// Returns the changed values per key; unchanged keys are omitted.
+    fn between(before: &PageMeta, after: &PageMeta) -> BTreeMap<String, MetaChange> {
+        let before = before.to_map();
+        let after = after.to_map();
+        before
+            .keys()
+            .chain(after.keys())
+            .collect::<BTreeSet<&String>>()
+            .into_iter()
+            .filter_map(|key| {
+                let change = MetaChange {
+                    before: before.get(key).cloned(),
+                    after: after.get(key).cloned(),
+                };
+                (change.before != change.after).then(|| (key.to_owned(), change))
+            })
+            .collect()
+    }
+
+    pub fn as_json(&self) -> Value {
+        json!({"before": self.before, "after": self.after})
+    }
+}
+
+/// A page whose metadata changed between two lists, with the newer version of the page.
+#[derive(Debug, Clone)]
+pub struct PageChange {
+    pub page: Page,
+    pub changes: BTreeMap<String, MetaChange>,
+}
+
+/// The differences between two `PageList`s of the same site, as returned by `PageList::diff`.
+#[derive(Debug, Clone)]
+pub struct PageListDiff {
+    site: Site,
+    added: Vec<Page>,
+    removed: Vec<Page>,
+    changed: Vec<PageChange>,
+}
+
+impl PageListDiff {
+    pub fn site(&self) -> &Site {
+        &self.site
+    }
+
+    /// Pages that are only in the newer list.
+    pub fn added(&self) -> &[Page] {
+        &self.added
+    }
+
+    /// Pages that are only in the older list.
+    pub fn removed(&self) -> &[Page] {
+        &self.removed
+    }
+
+    /// Pages that are in both lists, with different metadata.
+    pub fn changed(&self) -> &[PageChange] {
+        &self.changed
+    }
+
+    /// Returns `true` if both lists have the same pages and metadata.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Returns the diff as JSON, using the namespace table of the site (see `NamespaceTable::for_site`).
+    pub async fn as_json(&self) -> Value {
+        let namespaces = NamespaceTable::for_site(&self.site).await;
+        self.as_json_with(&namespaces)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `as_json`, in a blocking manner.
+    pub fn as_json_blocking(&self) -> Value {
+        let namespaces = NamespaceTable::for_site_blocking(&self.site);
+        self.as_json_with(&namespaces)
+    }
+
+    /// Returns the diff as JSON, with pages in the same format as `PageList::as_json_with`.
+    /// Changed pages have an additional `changes` object, with `before` and `after` values per key.
+    pub fn as_json_with(&self, namespaces: &NamespaceTable) -> Value {
+        let pages_json = |pages: &[Page]| {
+            pages
+                .iter()
+                .map(|page| page.as_json(namespaces))
+                .collect::<Vec<Value>>()
+        };
+        let changed = self
+            .changed
+            .iter()
+            .map(|change| {
+                let mut json = change.page.as_json(namespaces);
+                json["changes"] = change
+                    .changes
+                    .iter()
+                    .map(|(key, change)| (key.to_owned(), change.as_json()))
+                    .collect::<Map<String, Value>>()
+                    .into();
+                json
+            })
+            .collect::<Vec<Value>>();
+        json!({
+            "added": pages_json(&self.added),
+            "removed": pages_json(&self.removed),
+            "changed": changed,
+            "site": self.site,
+        })
+    }
+}
+
+/// Implemented by tools whose results are a list of pages.
+#[async_trait]
+pub trait PageListSource: Sync {
+    /// Returns the site the pages are on, if known.
+    fn page_list_site(&self) -> Option<Site>;
+
+    /// Builds a `PageList` from the results of the last run.
+    /// `namespaces` belongs to `page_list_site`, and is used to parse namespace-prefixed titles.
+    fn page_list_with(&self, namespaces: &NamespaceTable) -> Result<PageList, ToolsError>;
+
+    #[cfg(feature = "tokio")]
+    /// Builds a `PageList` from the results of the last run.
+    async fn page_list(&self) -> Result<PageList, ToolsError> {
+        let site = self
+            .page_list_site()
+            .ok_or_else(|| ToolsError::Tool("Site of the results is not known".to_string()))?;
+        let namespaces = NamespaceTable::for_site(&site).await;
+        self.page_list_with(&namespaces)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `page_list`, in a blocking manner.
+    fn page_list_blocking(&self) -> Result<PageList, ToolsError> {
+        let site = self
+            .page_list_site()
+            .ok_or_else(|| ToolsError::Tool("Site of the results is not known".to_string()))?;
+        let namespaces = NamespaceTable::for_site_blocking(&site);
+        self.page_list_with(&namespaces)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::replay::Replay;
 
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_subset() {
         let pl1 = PageList::from_file("test_data/pagelist1.json").unwrap();
         let pl2 = PageList::from_file("test_data/pagelist2.json").unwrap();
-        let pl3 = pl1.subset(&pl2).await;
+        let pl3 = pl1.subset(&pl2).await.unwrap();
         assert_eq!(pl3.pages.len(), 1);
     }
 
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_union() {
         let pl1 = PageList::from_file("test_data/pagelist1.json").unwrap();
         let pl2 = PageList::from_file("test_data/pagelist2.json").unwrap();
-        let pl3 = pl1.union(&pl2).await;
+        let pl3 = pl1.union(&pl2).await.unwrap();
         assert_eq!(pl3.pages.len(), 12);
     }
 
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_subset_union_blocking() {
+        let pl1 = PageList::from_file("test_data/pagelist1.json").unwrap();
+        let pl2 = PageList::from_file("test_data/pagelist2.json").unwrap();
+        assert_eq!(pl1.subset_blocking(&pl2).unwrap().pages.len(), 1);
+        assert_eq!(pl1.union_blocking(&pl2).unwrap().pages.len(), 12);
+    }
+
+    #[test]
+    fn test_page_meta() {
+        let map = json!({"wikidata":"Q42","counter":3,"lat":1.5,"lon":2.5,"foo":"bar"});
+        let meta = PageMeta::from_map(map.as_object().unwrap().to_owned());
+        assert_eq!(meta.wikidata.as_deref(), Some("Q42"));
+        assert_eq!(meta.counter, Some(3));
+        assert_eq!(meta.coordinates, Some((1.5, 2.5)));
+        assert_eq!(meta.extra.len(), 1);
+        assert_eq!(Value::Object(meta.to_map()), map);
+    }
+
+    #[test]
+    fn test_page_meta_keeps_invalid_values() {
+        let map = json!({"lat":1.5,"page_id":"12","size":-3,"counter":2.5,"timestamp":20240101,"wikidata":null});
+        let meta = PageMeta::from_map(map.as_object().unwrap().to_owned());
+        assert_eq!(meta.coordinates, None);
+        assert_eq!(meta.page_id, None);
+        assert_eq!(meta.size, None);
+        assert_eq!(meta.counter, None);
+        assert_eq!(meta.timestamp, None);
+        assert_eq!(meta.wikidata, None);
+        assert_eq!(meta.extra.len(), 6);
+        assert_eq!(Value::Object(meta.to_map()), map);
+
+        let meta = PageMeta::from_map(json!({"lon":2.5}).as_object().unwrap().to_owned());
+        assert_eq!(meta.coordinates, None);
+        assert_eq!(meta.extra["lon"], json!(2.5));
+    }
+
+    #[test]
+    fn test_as_json_with() {
+        let namespaces = NamespaceTable::bundled("enwiki").unwrap();
+        let page = Page::from_prefixed("wikipedia_talk:Foo", &namespaces, PageMeta::default());
+        let pl = PageList::new(Site::from_wiki("enwiki").unwrap(), vec![page]);
+        let json = pl.as_json_with(&namespaces);
+        assert_eq!(json["pages"][0]["prefixed_title"], "Wikipedia talk:Foo");
+        assert_eq!(json["pages"][0]["namespace_id"], 5);
+        assert_eq!(PageList::from_json(&json).unwrap().pages.len(), 1);
+        assert!(json.get("meta").is_none());
+    }
+
+    #[test]
+    fn test_provenance_roundtrip() {
+        let namespaces = NamespaceTable::bundled("enwiki").unwrap();
+        let provenance = Provenance {
+            tool: "pagepile".to_string(),
+            parameters: json!({"id": 12345}),
+            timestamp: chrono::Utc::now(),
+        };
+        let pl = PageList::new(Site::from_wiki("enwiki").unwrap(), vec![])
+            .with_provenance(provenance.clone());
+        let json = pl.as_json_with(&namespaces);
+        assert_eq!(json["meta"]["tool"], "pagepile");
+        let pl2 = PageList::from_json(&json).unwrap();
+        assert_eq!(pl2.provenance(), Some(&provenance));
+
+        let mut json = json;
+        json["meta"] = json!({"tool": "pagepile"});
+        assert!(matches!(
+            PageList::from_json(&json),
+            Err(ToolsError::Parse { .. })
+        ));
+        json.as_object_mut().unwrap().remove("meta");
+        assert!(PageList::from_json(&json).unwrap().provenance().is_none());
+    }
+
+    #[test]
+    fn test_diff() {
+        let namespaces = NamespaceTable::bundled("enwiki").unwrap();
+        let site = Site::from_wiki("enwiki").unwrap();
+        let page = |title: &str, counter: u64| {
+            let meta = PageMeta {
+                counter: Some(counter),
+                ..Default::default()
+            };
+            Page::from_prefixed(title, &namespaces, meta)
+        };
+        let old = PageList::new(
+            site.clone(),
+            vec![page("Foo", 1), page("Bar", 2), page("Talk:Baz", 3)],
+        );
+        let new = PageList::new(
+            site.clone(),
+            vec![page("Foo", 1), page("Bar", 5), page("Qux", 1)],
+        );
+        let diff = old.diff(&new).unwrap();
+        assert_eq!(diff.added().len(), 1);
+        assert_eq!(diff.added()[0].title().pretty(), "Qux");
+        assert_eq!(diff.removed().len(), 1);
+        assert_eq!(diff.removed()[0].title().namespace_id(), 1);
+        assert_eq!(diff.changed().len(), 1);
+        assert_eq!(
+            diff.changed()[0].changes["counter"],
+            MetaChange {
+                before: Some(json!(2)),
+                after: Some(json!(5)),
+            }
+        );
+
+        let json = diff.as_json_with(&namespaces);
+        assert_eq!(json["removed"][0]["prefixed_title"], "Talk:Baz");
+        assert_eq!(json["changed"][0]["counter"], 5);
+        assert_eq!(json["changed"][0]["changes"]["counter"]["before"], 2);
+        assert!(old.diff(&old).unwrap().is_empty());
+
+        let other_site = PageList::new(Site::from_wiki("dewiki").unwrap(), vec![]);
+        assert!(old.diff(&other_site).is_err());
+    }
+
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_to_wiki() {
         let pl = PageList {
             site: Site::from_wiki("enwiki").unwrap(),
+            provenance: None,
             pages: vec![
                 Page {
                     title: Title::new("Biochemistry", 0),
-                    meta: Map::new(),
+                    meta: PageMeta::default(),
                 },
                 Page {
                     title: Title::new("Magnus_Manske", 0),
-                    meta: Map::new(),
+                    meta: PageMeta::default(),
                 },
             ],
         };
-        let pl2 = pl.to_wiki("dewiki").await;
+        let replay = Replay::start("page_list_to_wiki").await;
+        let pl2 = pl.to_wiki_with(&replay, "dewiki").await.unwrap();
         assert_eq!(pl2.pages.len(), 2);
         assert_eq!(pl2.pages[0].title.pretty(), "Biochemie");
         assert_eq!(pl2.pages[1].title.pretty(), "Magnus Manske");
     }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_to_wiki_error() {
+        use crate::RetryPolicy;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/change_wiki/enwiki/dewiki"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::WdInfernal, server.uri())
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        let pl = PageList::new(
+            Site::from_wiki("enwiki").unwrap(),
+            vec![Page::new(
+                Title::new("Biochemistry", 0),
+                PageMeta::default(),
+            )],
+        );
+        let result = pl.to_wiki_with(&ti, "dewiki").await;
+        assert!(matches!(
+            result,
+            Err(ToolsError::HttpStatus { status: 503, .. })
+        ));
+    }
 }
@@ -10,11 +10,17 @@
 /// let wiki = pp.wiki().unwrap();
 /// let page_titles = pp.prefixed_titles();
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, Paginated, ResultPage, Site, Tool, ToolRequest,
+    ToolResponse, ToolsError, ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
-use serde_json::{Value, json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PagePile {
     id: u32,
 
@@ -60,25 +66,27 @@ impl PagePile {
             None => Site::from_language_project(self.language.as_ref()?, self.project.as_ref()?),
         })
     }
-
-    pub async fn as_json(&self) -> Option<Value> {
-        let site = self.site()?;
-        let api = site.api().await.ok()?;
-        Some(json!({
-            "pages": self.prefixed_titles()
-                .iter()
-                .map(|prefixed_title| FancyTitle::from_prefixed(prefixed_title, &api).to_json())
-                .collect::<Vec<Value>>(),
-            "site": site,
-        }))
-    }
 }
 
 #[async_trait]
 impl Tool for PagePile {
-    fn get_url(&self) -> String {
+    type Output = Vec<String>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        Ok(ToolRequest::get(self.get_url(ti)))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.prefixed_titles.clone())
+    }
+}
+
+impl PagePile {
+    pub fn get_url(&self, ti: &ToolsInterface) -> String {
         format!(
-            "https://pagepile.toolforge.org/api.php?id={id}&action=get_data&doit&format=json",
+            "{base}/api.php?id={id}&action=get_data&doit&format=json",
+            base = ti.endpoint(Endpoint::PagePile),
             id = self.id
         )
     }
@@ -87,39 +95,105 @@ impl Tool for PagePile {
         self.language = j["language"].as_str().map(|s| s.to_string());
         self.project = j["project"].as_str().map(|s| s.to_string());
         self.wiki = j["wiki"].as_str().map(|s| s.to_string());
-        self.prefixed_titles = j["pages"]
-            .as_array()
-            .ok_or(ToolsError::Json("['pages'] has no rows array".into()))?
-            .iter()
-            .filter_map(|page| page.as_str())
-            .map(|prefixed_title| prefixed_title.to_string())
-            .collect();
-        let pages_returned = j["pages_returned"].as_i64().ok_or(ToolsError::Json(
-            "['pages_returned'] is not an integer".into(),
-        ))?;
+        self.prefixed_titles = Self::prefixed_titles_from_json(&j)?;
+        let pages_returned = j["pages_returned"]
+            .as_i64()
+            .ok_or_else(|| ToolsError::parse("PagePile", "pages_returned", "not an integer"))?;
         let pages_total = j["pages_total"]
             .as_i64()
-            .ok_or(ToolsError::Json("['pages_total'] is not an integer".into()))?;
+            .ok_or_else(|| ToolsError::parse("PagePile", "pages_total", "not an integer"))?;
         if pages_returned != pages_total {
-            return Err(ToolsError::Json(format!(
-                "pages_returned ({}) != pages_total ({})",
-                pages_returned, pages_total
-            )));
+            return Err(ToolsError::parse(
+                "PagePile",
+                "pages_returned",
+                format!(
+                    "pages_returned ({pages_returned}) != pages_total ({pages_total}), use `Paginated::stream` to get all pages"
+                ),
+            ));
         }
         if pages_total != self.prefixed_titles.len() as i64 {
-            return Err(ToolsError::Json(format!(
-                "pages_total ({}) != prefixed_titles.len() ({})",
-                pages_total,
-                self.prefixed_titles.len()
-            )));
+            return Err(ToolsError::parse(
+                "PagePile",
+                "pages",
+                format!(
+                    "pages_total ({pages_total}) != number of pages ({})",
+                    self.prefixed_titles.len()
+                ),
+            ));
         }
         Ok(())
     }
+
+    fn prefixed_titles_from_json(j: &Value) -> Result<Vec<String>, ToolsError> {
+        Ok(j["pages"]
+            .as_array()
+            .ok_or_else(|| ToolsError::parse("PagePile", "pages", "not an array"))?
+            .iter()
+            .filter_map(|page| page.as_str())
+            .map(|prefixed_title| prefixed_title.to_string())
+            .collect())
+    }
+}
+
+/// Pages through the namespace-prefixed titles of the PagePile, using the `offset` parameter.
+impl Paginated for PagePile {
+    type Item = String;
+    type Cursor = usize;
+
+    fn page_request(
+        &self,
+        ti: &ToolsInterface,
+        cursor: Option<&usize>,
+    ) -> Result<ToolRequest, ToolsError> {
+        let mut request = ToolRequest::get(self.get_url(ti));
+        if let Some(offset) = cursor {
+            request = request.query(&[("offset".to_string(), offset.to_string())]);
+        }
+        Ok(request)
+    }
+
+    fn parse_page(
+        &self,
+        response: &ToolResponse,
+        cursor: Option<&usize>,
+    ) -> Result<ResultPage<String, usize>, ToolsError> {
+        let j = response.json()?;
+        let items = Self::prefixed_titles_from_json(&j)?;
+        let pages_total = j["pages_total"]
+            .as_u64()
+            .ok_or_else(|| ToolsError::parse("PagePile", "pages_total", "not an integer"))?;
+        let offset = cursor.copied().unwrap_or(0) + items.len();
+        Ok(ResultPage {
+            items,
+            next: Some(offset).filter(|offset| (*offset as u64) < pages_total),
+        })
+    }
+}
+
+impl PageListSource for PagePile {
+    fn page_list_site(&self) -> Option<Site> {
+        self.site()
+    }
+
+    fn page_list_with(&self, namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let site = self
+            .site()
+            .ok_or_else(|| ToolsError::parse("PagePile", "wiki", "missing or unknown"))?;
+        let pages = self
+            .prefixed_titles
+            .iter()
+            .map(|prefixed_title| {
+                Page::from_prefixed(prefixed_title, namespaces, PageMeta::default())
+            })
+            .collect();
+        Ok(PageList::new(site, pages))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::replay::Replay;
 
     #[test]
     fn test_pagepile_new() {
@@ -130,22 +204,55 @@ mod tests {
     #[cfg(feature = "blocking")]
     #[test]
     fn test_pagepile_get_blocking() {
+        let replay = Replay::start_blocking("pagepile_51805");
         let mut pp = PagePile::new(51805);
-        pp.run_blocking().unwrap();
+        pp.run_blocking_with(&replay).unwrap();
         assert_eq!(pp.language().unwrap(), "de");
         assert_eq!(pp.project().unwrap(), "wikipedia");
         assert_eq!(pp.wiki().unwrap(), "dewiki");
-        assert_eq!(pp.prefixed_titles().len(), 1747);
+        assert_eq!(pp.prefixed_titles().len(), 8);
+        assert_eq!(pp.prefixed_titles()[5], "Ökologie");
     }
 
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_pagepile_get_async() {
+        let replay = Replay::start("pagepile_51805").await;
         let mut pp = PagePile::new(51805);
-        pp.run().await.unwrap();
+        pp.run_with(&replay).await.unwrap();
         assert_eq!(pp.language().unwrap(), "de");
         assert_eq!(pp.project().unwrap(), "wikipedia");
         assert_eq!(pp.wiki().unwrap(), "dewiki");
-        assert_eq!(pp.prefixed_titles().len(), 1747);
+        assert_eq!(pp.prefixed_titles().len(), 8);
+        assert_eq!(pp.prefixed_titles()[0], "Biologie");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_pagepile_stream() {
+        use futures::TryStreamExt;
+        use wiremock::matchers::{query_param, query_param_is_missing};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let first = r#"{"wiki":"dewiki","pages":["A","B"],"pages_returned":2,"pages_total":3}"#;
+        let second = r#"{"wiki":"dewiki","pages":["C"],"pages_returned":1,"pages_total":3}"#;
+        Mock::given(query_param_is_missing("offset"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(first))
+            .mount(&server)
+            .await;
+        Mock::given(query_param("offset", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(second))
+            .mount(&server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::PagePile, server.uri())
+            .build()
+            .unwrap();
+        let pp = PagePile::new(1);
+        let titles: Vec<String> = pp.stream(&ti, None).try_collect().await.unwrap();
+        assert_eq!(titles, ["A", "B", "C"]);
+        let titles: Vec<String> = pp.stream(&ti, Some(1)).try_collect().await.unwrap();
+        assert_eq!(titles, ["A"]);
     }
 }
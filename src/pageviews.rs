@@ -1,11 +1,12 @@
 /// # Pageviews
 /// This implements a simple interface to the Wikimedia Pageviews API.
 /// More information can be found [here](https://wikitech.wikimedia.org/wiki/Analytics/AQS/Pageviews).
-/// Currently, only single-page views are supported.
-/// Aggregate and top views are not yet implemented.
+/// Supported are views of single pages, aggregated views of whole projects (or all projects),
+/// and the most viewed articles of a project or country, and the countries with the most views of a project.
 ///
 /// ## Features
 /// Views for multiple pages, on multiple projects, can be retrieved concurrently for a single time span.
+/// All methods have `_blocking` counterparts.
 ///
 /// ## Example
 /// ```ignore
@@ -31,14 +32,32 @@
 ///
 /// // Count all views of all pages.
 /// let overall_views: u64 = results.iter().map(|r| r.total_views()).sum();
+///
+/// // Compare with the views of the whole project.
+/// let project = pv.get_aggregate(
+///     "de.wikipedia",
+///     &Pageviews::month_start(2016, 1).unwrap(),
+///     &Pageviews::month_end(2016, 12).unwrap(),
+/// ).await.unwrap();
+/// let share = overall_views as f64 / project.total_views() as f64;
+///
+/// // The most viewed articles in December 2016, as a page list.
+/// let top = pv.get_top("de.wikipedia", &Pageviews::month_start(2016, 12).unwrap()).await.unwrap();
+/// let page_list = top.page_list().await.unwrap();
 /// ```
 // NOTE: This does not use the `Tool` trait, it is too different.
+use crate::{
+    NamespaceTable, PageListSource, Site, ToolsError,
+    page_list::{Page, PageList, PageMeta},
+};
 use chrono::{Duration, NaiveDate};
+#[cfg(feature = "tokio")]
 use futures::prelude::*;
-use serde::Deserialize;
-use serde_json::Value;
+use mediawiki::title::Title;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub enum PageviewsAccess {
     #[serde(rename = "all-access")]
     All,
@@ -61,7 +80,7 @@ impl PageviewsAccess {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub enum PageviewsAgent {
     #[serde(rename = "all-agents")]
     All,
@@ -84,7 +103,7 @@ impl PageviewsAgent {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub enum PageviewsGranularity {
     #[serde(rename = "hourly")]
     Hourly,
@@ -104,7 +123,7 @@ impl PageviewsGranularity {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct PageviewsTimestamp {
     year: u16,
     month: u8,
@@ -132,7 +151,7 @@ impl From<PageviewsTimestamp> for String {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PageviewsParams {
     pub timestamp: PageviewsTimestamp,
     pub views: u64,
@@ -148,7 +167,7 @@ impl PageviewsParams {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PageviewsResult {
     pub project: String,
     pub article: String,
@@ -172,14 +191,127 @@ impl PageviewsResult {
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// Aggregated views of a project, or of all projects.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageviewsAggregateResult {
+    pub project: String,
+    pub granularity: PageviewsGranularity,
+    pub access: PageviewsAccess,
+    pub agent: PageviewsAgent,
+    pub entries: Vec<PageviewsParams>,
+}
+
+impl PageviewsAggregateResult {
+    pub fn total_views(&self) -> u64 {
+        self.entries.iter().map(|r| r.views).sum::<u64>()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// An article in a ranked list of the most viewed articles.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageviewsTopArticle {
+    pub project: String,
+    pub article: String,
+    pub views: u64,
+    pub rank: u32,
+}
+
+/// The most viewed articles of a project (`get_top`), or of a country (`get_top_per_country`).
+/// Views per country are rounded up by the API.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageviewsTopArticles {
+    pub project: Option<String>,
+    pub country: Option<String>,
+    pub access: PageviewsAccess,
+    pub articles: Vec<PageviewsTopArticle>,
+}
+
+impl PageviewsTopArticles {
+    pub fn len(&self) -> usize {
+        self.articles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the project of the articles, if they are all from the same project.
+    fn common_project(&self) -> Option<&str> {
+        if let Some(project) = &self.project {
+            return Some(project);
+        }
+        let project = &self.articles.first()?.project;
+        match self.articles.iter().all(|a| a.project == *project) {
+            true => Some(project),
+            false => None,
+        }
+    }
+}
+
+/// Pages with `views` and `rank` metadata, in rank order.
+/// Only available if all articles are from the same project.
+impl PageListSource for PageviewsTopArticles {
+    fn page_list_site(&self) -> Option<Site> {
+        Site::from_webserver(&format!("{}.org", self.common_project()?))
+    }
+
+    fn page_list_with(&self, namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let site = self
+            .page_list_site()
+            .ok_or_else(|| ToolsError::parse("Pageviews", "project", "mixed or unknown"))?;
+        let pages = self
+            .articles
+            .iter()
+            .map(|article| {
+                let meta = PageMeta::default()
+                    .with_extra("views", json!(article.views))
+                    .with_extra("rank", json!(article.rank));
+                let title = Title::underscores_to_spaces(&article.article);
+                Page::from_prefixed(&title, namespaces, meta)
+            })
+            .collect();
+        Ok(PageList::new(site, pages))
+    }
+}
+
+/// A country in a ranked list of the countries with the most views of a project.
+/// Views are rounded up by the API.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageviewsTopCountry {
+    pub country: String,
+    pub views: u64,
+    pub rank: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Pageviews {
     granularity: PageviewsGranularity,
     access: PageviewsAccess,
     agent: PageviewsAgent,
+    #[serde(skip, default = "crate::ToolsInterface::global")]
+    interface: crate::ToolsInterface,
+}
+
+impl PartialEq for Pageviews {
+    fn eq(&self, other: &Self) -> bool {
+        self.granularity == other.granularity
+            && self.access == other.access
+            && self.agent == other.agent
+    }
 }
 
 impl Pageviews {
+    /// The "project" for aggregated views of all projects.
+    pub const ALL_PROJECTS: &str = "all-projects";
+
     // Returns a `NaiveDate` representing the first day of the month.
     pub fn month_start(year: i32, month: u32) -> Option<NaiveDate> {
         NaiveDate::from_ymd_opt(year, month, 1)
@@ -203,13 +335,20 @@ impl Pageviews {
             granularity,
             access,
             agent,
+            interface: crate::ToolsInterface::global(),
         }
     }
 
+    /// Use the given `ToolsInterface` instead of the process-wide one.
+    pub fn with_interface(mut self, interface: crate::ToolsInterface) -> Self {
+        self.interface = interface;
+        self
+    }
+
     #[cfg(feature = "tokio")]
     /// Get pageviews for a single page.
     /// The result page title will have underscores ("_") instead of spaces.
-    /// This function will automatically retry if the Wikimedia API returns a 429 (throttling) status code.
+    /// Throttled (429) requests are retried according to the `RetryPolicy` of the `ToolsInterface`.
     pub async fn get_per_article<S1: Into<String>, S2: Into<String>>(
         &self,
         page: S1,
@@ -219,61 +358,25 @@ impl Pageviews {
     ) -> Result<PageviewsResult, crate::ToolsError> {
         let project: String = project.into();
         let page: String = page.into().replace(" ", "_");
-        let url = format!("https://wikimedia.org/api/rest_v1/metrics/pageviews/per-article/{project}/{access}/{agent}/{page}/{granularity}/{start}/{end}",
-            access=self.access.as_str(),
-            agent=self.agent.as_str(),
-            granularity=self.granularity.as_str(),
-            start=start.format("%Y%m%d"),
-            end=end.format("%Y%m%d"),
-        );
-        let client = crate::ToolsInterface::tokio_client()?;
-        let json: Value;
-        loop {
-            let response = client.get(&url).send().await?;
-            let status = response.status();
-            if status == 429 {
-                // Throttling
-                let delay = response
-                    .headers()
-                    .get("Retry-After")
-                    .and_then(|s| s.to_str().ok())
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(5);
-                tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
-                continue;
-            }
-            json = response.json().await?;
-            break;
-        }
-        if json.get("status").is_some() {
-            let message = match json.get("detail") {
-                Some(detail) => match detail.as_str() {
-                    Some(detail_str) => detail_str.to_string(),
-                    None => detail.to_string(), // Not a string, fallback
-                },
-                None => json["status"].to_string(), // We know this exists, fallback
-            };
-            return Err(crate::ToolsError::Tool(message));
-        }
-        let items = json
-            .get("items")
-            .ok_or_else(|| crate::ToolsError::Json("No 'items' in Pageviews JSON".to_string()))?
-            .as_array()
-            .ok_or_else(|| {
-                crate::ToolsError::Json("'items' is not an array in Pageviews JSON".to_string())
-            })?;
-        let ret = PageviewsResult {
-            project,
-            article: page,
-            granularity: self.granularity.to_owned(),
-            access: self.access.to_owned(),
-            agent: self.agent.to_owned(),
-            entries: items
-                .iter()
-                .filter_map(PageviewsParams::from_json)
-                .collect(),
-        };
-        Ok(ret)
+        let request = self.per_article_request(&page, &project, start, end);
+        let json = self.interface.execute_checked(&request).await?.json()?;
+        self.parse_per_article(page, project, json)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `get_per_article`, in a blocking manner.
+    pub fn get_per_article_blocking<S1: Into<String>, S2: Into<String>>(
+        &self,
+        page: S1,
+        project: S2,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<PageviewsResult, crate::ToolsError> {
+        let project: String = project.into();
+        let page: String = page.into().replace(" ", "_");
+        let request = self.per_article_request(&page, &project, start, end);
+        let json = self.interface.execute_checked_blocking(&request)?.json()?;
+        self.parse_per_article(page, project, json)
     }
 
     #[cfg(feature = "tokio")]
@@ -298,14 +401,318 @@ impl Pageviews {
         Ok(results.into_iter().filter_map(|r| r.ok()).collect())
     }
 
-    // TODO aggregate (all-projects)
-    // TODO top
-    // TODO top-per-country
+    #[cfg(feature = "blocking")]
+    /// Same as `get_multiple_articles`, in a blocking manner.
+    /// Pages are queried one after another, in the given order.
+    /// Failed requests will be silently ignored.
+    pub fn get_multiple_articles_blocking(
+        &self,
+        project_pages: &[(String, String)],
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<Vec<PageviewsResult>, crate::ToolsError> {
+        Ok(project_pages
+            .iter()
+            .filter_map(|(project, page)| {
+                self.get_per_article_blocking(page, project, start, end)
+                    .ok()
+            })
+            .collect())
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Get the aggregated views of a project, e.g. "de.wikipedia".
+    /// Use `Pageviews::ALL_PROJECTS` for the views of all projects.
+    pub async fn get_aggregate<S: Into<String>>(
+        &self,
+        project: S,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<PageviewsAggregateResult, crate::ToolsError> {
+        let project: String = project.into();
+        let request = self.aggregate_request(&project, start, end);
+        let json = self.interface.execute_checked(&request).await?.json()?;
+        self.parse_aggregate(project, json)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `get_aggregate`, in a blocking manner.
+    pub fn get_aggregate_blocking<S: Into<String>>(
+        &self,
+        project: S,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<PageviewsAggregateResult, crate::ToolsError> {
+        let project: String = project.into();
+        let request = self.aggregate_request(&project, start, end);
+        let json = self.interface.execute_checked_blocking(&request)?.json()?;
+        self.parse_aggregate(project, json)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Get the most viewed articles of a project, e.g. "de.wikipedia".
+    /// With `Monthly` granularity, the views of the month of `date` are used, otherwise the views of the day.
+    /// Uses the access method, but not the agent; the API only counts users here.
+    pub async fn get_top<S: Into<String>>(
+        &self,
+        project: S,
+        date: &NaiveDate,
+    ) -> Result<PageviewsTopArticles, crate::ToolsError> {
+        let project: String = project.into();
+        let request = self.top_request("top", &project, date, true);
+        let json = self.interface.execute_checked(&request).await?.json()?;
+        self.parse_top_articles(Some(project), None, json)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `get_top`, in a blocking manner.
+    pub fn get_top_blocking<S: Into<String>>(
+        &self,
+        project: S,
+        date: &NaiveDate,
+    ) -> Result<PageviewsTopArticles, crate::ToolsError> {
+        let project: String = project.into();
+        let request = self.top_request("top", &project, date, true);
+        let json = self.interface.execute_checked_blocking(&request)?.json()?;
+        self.parse_top_articles(Some(project), None, json)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Get the most viewed articles in a country, given as ISO 3166-1 alpha-2 code (e.g. "DE"), across all projects.
+    /// With `Monthly` granularity, the views of the month of `date` are used, otherwise the views of the day.
+    pub async fn get_top_per_country<S: Into<String>>(
+        &self,
+        country: S,
+        date: &NaiveDate,
+    ) -> Result<PageviewsTopArticles, crate::ToolsError> {
+        let country: String = country.into();
+        let request = self.top_request("top-per-country", &country, date, true);
+        let json = self.interface.execute_checked(&request).await?.json()?;
+        self.parse_top_articles(None, Some(country), json)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `get_top_per_country`, in a blocking manner.
+    pub fn get_top_per_country_blocking<S: Into<String>>(
+        &self,
+        country: S,
+        date: &NaiveDate,
+    ) -> Result<PageviewsTopArticles, crate::ToolsError> {
+        let country: String = country.into();
+        let request = self.top_request("top-per-country", &country, date, true);
+        let json = self.interface.execute_checked_blocking(&request)?.json()?;
+        self.parse_top_articles(None, Some(country), json)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Get the countries with the most views of a project, e.g. "de.wikipedia", in the month of `date`.
+    pub async fn get_top_by_country<S: Into<String>>(
+        &self,
+        project: S,
+        date: &NaiveDate,
+    ) -> Result<Vec<PageviewsTopCountry>, crate::ToolsError> {
+        let request = self.top_request("top-by-country", &project.into(), date, false);
+        let json = self.interface.execute_checked(&request).await?.json()?;
+        Self::parse_top_countries(json)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `get_top_by_country`, in a blocking manner.
+    pub fn get_top_by_country_blocking<S: Into<String>>(
+        &self,
+        project: S,
+        date: &NaiveDate,
+    ) -> Result<Vec<PageviewsTopCountry>, crate::ToolsError> {
+        let request = self.top_request("top-by-country", &project.into(), date, false);
+        let json = self.interface.execute_checked_blocking(&request)?.json()?;
+        Self::parse_top_countries(json)
+    }
+
+    fn per_article_request(
+        &self,
+        page: &str,
+        project: &str,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> crate::ToolRequest {
+        let url = format!(
+            "{base}/metrics/pageviews/per-article/{project}/{access}/{agent}/{page}/{granularity}/{start}/{end}",
+            base = self.interface.endpoint(crate::Endpoint::Pageviews),
+            access = self.access.as_str(),
+            agent = self.agent.as_str(),
+            granularity = self.granularity.as_str(),
+            start = start.format("%Y%m%d"),
+            end = end.format("%Y%m%d"),
+        );
+        crate::ToolRequest::get(url)
+    }
+
+    fn aggregate_request(
+        &self,
+        project: &str,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> crate::ToolRequest {
+        let url = format!(
+            "{base}/metrics/pageviews/aggregate/{project}/{access}/{agent}/{granularity}/{start}/{end}",
+            base = self.interface.endpoint(crate::Endpoint::Pageviews),
+            access = self.access.as_str(),
+            agent = self.agent.as_str(),
+            granularity = self.granularity.as_str(),
+            start = start.format("%Y%m%d"),
+            end = end.format("%Y%m%d"),
+        );
+        crate::ToolRequest::get(url)
+    }
+
+    /// `scope` is the project or country; `with_day` adds the day, or "all-days" for `Monthly` granularity.
+    fn top_request(
+        &self,
+        metric: &str,
+        scope: &str,
+        date: &NaiveDate,
+        with_day: bool,
+    ) -> crate::ToolRequest {
+        let mut url = format!(
+            "{base}/metrics/pageviews/{metric}/{scope}/{access}/{year}",
+            base = self.interface.endpoint(crate::Endpoint::Pageviews),
+            access = self.access.as_str(),
+            year = date.format("%Y/%m"),
+        );
+        if with_day {
+            match self.granularity {
+                PageviewsGranularity::Monthly => url += "/all-days",
+                _ => url += &date.format("/%d").to_string(),
+            }
+        }
+        crate::ToolRequest::get(url)
+    }
+
+    fn parse_per_article(
+        &self,
+        page: String,
+        project: String,
+        json: Value,
+    ) -> Result<PageviewsResult, crate::ToolsError> {
+        let ret = PageviewsResult {
+            project,
+            article: page,
+            granularity: self.granularity.to_owned(),
+            access: self.access.to_owned(),
+            agent: self.agent.to_owned(),
+            entries: Self::entries_from_json(&json)?,
+        };
+        Ok(ret)
+    }
+
+    fn parse_aggregate(
+        &self,
+        project: String,
+        json: Value,
+    ) -> Result<PageviewsAggregateResult, crate::ToolsError> {
+        Ok(PageviewsAggregateResult {
+            project,
+            granularity: self.granularity.to_owned(),
+            access: self.access.to_owned(),
+            agent: self.agent.to_owned(),
+            entries: Self::entries_from_json(&json)?,
+        })
+    }
+
+    fn parse_top_articles(
+        &self,
+        project: Option<String>,
+        country: Option<String>,
+        json: Value,
+    ) -> Result<PageviewsTopArticles, crate::ToolsError> {
+        let mut articles: Vec<PageviewsTopArticle> = Self::items_from_json(&json)?
+            .iter()
+            .filter_map(|item| Some((item, item.get("articles")?.as_array()?)))
+            .flat_map(|(item, articles)| articles.iter().map(move |article| (item, article)))
+            .filter_map(|(item, article)| {
+                // `top` has the project per item, `top-per-country` per article
+                let project = article
+                    .get("project")
+                    .or_else(|| item.get("project"))?
+                    .as_str()?;
+                Some(PageviewsTopArticle {
+                    project: project.to_string(),
+                    article: article.get("article")?.as_str()?.to_string(),
+                    views: Self::views_from_json(article)?,
+                    rank: article.get("rank")?.as_u64()? as u32,
+                })
+            })
+            .collect();
+        articles.sort_by_key(|article| article.rank);
+        Ok(PageviewsTopArticles {
+            project,
+            country,
+            access: self.access.to_owned(),
+            articles,
+        })
+    }
+
+    fn parse_top_countries(json: Value) -> Result<Vec<PageviewsTopCountry>, crate::ToolsError> {
+        let mut countries: Vec<PageviewsTopCountry> = Self::items_from_json(&json)?
+            .iter()
+            .filter_map(|item| item.get("countries")?.as_array())
+            .flatten()
+            .filter_map(|country| {
+                Some(PageviewsTopCountry {
+                    country: country.get("country")?.as_str()?.to_string(),
+                    views: Self::views_from_json(country)?,
+                    rank: country.get("rank")?.as_u64()? as u32,
+                })
+            })
+            .collect();
+        countries.sort_by_key(|country| country.rank);
+        Ok(countries)
+    }
+
+    /// Per-country views are rounded up (`views_ceil`); older responses have a range ("1000-9999"),
+    /// of which the lower bound is used.
+    fn views_from_json(item: &Value) -> Option<u64> {
+        if let Some(views) = item.get("views_ceil").and_then(|v| v.as_u64()) {
+            return Some(views);
+        }
+        let views = item.get("views")?;
+        match views.as_str() {
+            Some(range) => range.split('-').next()?.trim().parse().ok(),
+            None => views.as_u64(),
+        }
+    }
+
+    fn entries_from_json(json: &Value) -> Result<Vec<PageviewsParams>, crate::ToolsError> {
+        Ok(Self::items_from_json(json)?
+            .iter()
+            .filter_map(PageviewsParams::from_json)
+            .collect())
+    }
+
+    /// Returns the `items` of a response, or the error the API reported instead.
+    fn items_from_json(json: &Value) -> Result<&Vec<Value>, crate::ToolsError> {
+        if json.get("status").is_some() {
+            let message = match json.get("detail") {
+                Some(detail) => match detail.as_str() {
+                    Some(detail_str) => detail_str.to_string(),
+                    None => detail.to_string(), // Not a string, fallback
+                },
+                None => json["status"].to_string(), // We know this exists, fallback
+            };
+            return Err(crate::ToolsError::tool_status("Pageviews", message));
+        }
+        json.get("items")
+            .ok_or_else(|| crate::ToolsError::parse("Pageviews", "items", "missing"))?
+            .as_array()
+            .ok_or_else(|| crate::ToolsError::parse("Pageviews", "items", "not an array"))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::{ToolsInterface, replay::Replay};
 
     #[test]
     #[rustfmt::skip]
@@ -328,11 +735,13 @@ mod tests {
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_pageviews_get_per_article_monthly_async() {
+        let replay = Replay::start("pageviews_monthly").await;
         let pv = Pageviews::new(
             PageviewsGranularity::Monthly,
             PageviewsAccess::All,
             PageviewsAgent::All,
-        );
+        )
+        .with_interface(ToolsInterface::clone(&replay));
         let result = pv
             .get_per_article(
                 "Barack_Obama",
@@ -349,11 +758,13 @@ mod tests {
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_pageviews_get_per_article_daily_async() {
+        let replay = Replay::start("pageviews_daily").await;
         let pv = Pageviews::new(
             PageviewsGranularity::Daily,
             PageviewsAccess::All,
             PageviewsAgent::All,
-        );
+        )
+        .with_interface(ToolsInterface::clone(&replay));
         let result = pv
             .get_per_article(
                 "Barack_Obama",
@@ -370,11 +781,13 @@ mod tests {
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_pageviews_get_per_article_bad_date_async() {
+        let replay = Replay::start("pageviews_bad_date").await;
         let pv = Pageviews::new(
             PageviewsGranularity::Daily,
             PageviewsAccess::All,
             PageviewsAgent::All,
-        );
+        )
+        .with_interface(ToolsInterface::clone(&replay));
         let result = pv
             .get_per_article(
                 "Barack_Obama",
@@ -383,17 +796,22 @@ mod tests {
                 &Pageviews::month_end(1016, 1).unwrap(),
             )
             .await;
-        assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(crate::ToolsError::HttpStatus { status: 400, .. })
+        ));
     }
 
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_pageviews_multiple_articles_async() {
+        let replay = Replay::start("pageviews_multiple").await;
         let pv = Pageviews::new(
             PageviewsGranularity::Monthly,
             PageviewsAccess::All,
             PageviewsAgent::All,
-        );
+        )
+        .with_interface(ToolsInterface::clone(&replay));
         let project_pages = [
             ("de.wikipedia", "Barack Obama"),
             ("de.wikipedia", "Trude Herr"),
@@ -415,6 +833,139 @@ mod tests {
         assert_eq!(overall_views, 1_670_723);
     }
 
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_pageviews_aggregate_async() {
+        let replay = Replay::start("pageviews_aggregate").await;
+        let pv = Pageviews::new(
+            PageviewsGranularity::Monthly,
+            PageviewsAccess::All,
+            PageviewsAgent::User,
+        )
+        .with_interface(ToolsInterface::clone(&replay));
+        let result = pv
+            .get_aggregate(
+                Pageviews::ALL_PROJECTS,
+                &Pageviews::month_start(2024, 1).unwrap(),
+                &Pageviews::month_end(2024, 2).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.project, "all-projects");
+        assert_eq!(result.len(), 2);
+        assert_eq!(result.total_views(), 31_000_000_000);
+        assert_eq!(result.entries[1].timestamp, "2024020100".into());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_pageviews_aggregate_not_found_async() {
+        let replay = Replay::start("pageviews_aggregate_not_found").await;
+        let pv = Pageviews::new(
+            PageviewsGranularity::Daily,
+            PageviewsAccess::All,
+            PageviewsAgent::All,
+        )
+        .with_interface(ToolsInterface::clone(&replay));
+        let result = pv
+            .get_aggregate(
+                "xx.wikipedia",
+                &Pageviews::month_start(2024, 1).unwrap(),
+                &Pageviews::month_end(2024, 1).unwrap(),
+            )
+            .await;
+        match result {
+            Err(crate::ToolsError::HttpStatus {
+                tool,
+                status,
+                body_excerpt,
+                ..
+            }) => {
+                assert_eq!(tool, "Pageviews");
+                assert_eq!(status, 404);
+                assert!(body_excerpt.contains("Not found."));
+            }
+            other => panic!("Expected HttpStatus, got {other:?}"),
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_pageviews_top_async() {
+        let replay = Replay::start("pageviews_top").await;
+        let pv = Pageviews::new(
+            PageviewsGranularity::Monthly,
+            PageviewsAccess::All,
+            PageviewsAgent::User,
+        )
+        .with_interface(ToolsInterface::clone(&replay));
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let top = pv.get_top("en.wikipedia", &date).await.unwrap();
+        assert_eq!(top.len(), 3);
+        assert_eq!(top.articles[0].article, "Main_Page");
+        assert_eq!(top.articles[0].views, 150_000_000);
+
+        let namespaces = NamespaceTable::bundled("enwiki").unwrap();
+        let page_list = top.page_list_with(&namespaces).unwrap();
+        assert_eq!(page_list.site().wiki(), "enwiki");
+        let pages = page_list.pages();
+        assert_eq!(pages[0].title(), &Title::new("Main Page", 0));
+        assert_eq!(pages[1].title(), &Title::new("Search", -1));
+        assert_eq!(pages[2].meta().extra["rank"], json!(3));
+        assert_eq!(pages[2].meta().extra["views"], json!(7_000_000));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_pageviews_top_countries_async() {
+        let replay = Replay::start("pageviews_top_countries").await;
+        let pv = Pageviews::new(
+            PageviewsGranularity::Daily,
+            PageviewsAccess::All,
+            PageviewsAgent::All,
+        )
+        .with_interface(ToolsInterface::clone(&replay));
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+
+        let countries = pv.get_top_by_country("de.wikipedia", &date).await.unwrap();
+        assert_eq!(countries.len(), 2);
+        assert_eq!(countries[0].country, "DE");
+        assert_eq!(countries[0].views, 100_000_000);
+        assert_eq!(countries[1].views, 61_234_000);
+
+        let top = pv.get_top_per_country("DE", &date).await.unwrap();
+        assert_eq!(top.country.as_deref(), Some("DE"));
+        assert_eq!(top.articles[1].project, "en.wikipedia");
+        assert_eq!(top.articles[1].views, 20_000);
+        assert_eq!(top.page_list_site(), None); // Mixed projects
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_pageviews_top_not_found_async() {
+        let replay = Replay::start("pageviews_top_not_found").await;
+        let pv = Pageviews::new(
+            PageviewsGranularity::Daily,
+            PageviewsAccess::All,
+            PageviewsAgent::All,
+        )
+        .with_interface(ToolsInterface::clone(&replay));
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let is_not_found =
+            |e: &crate::ToolsError| matches!(e, crate::ToolsError::HttpStatus { status: 404, .. });
+        assert!(is_not_found(
+            &pv.get_top("xx.wikipedia", &date).await.unwrap_err()
+        ));
+        assert!(is_not_found(
+            &pv.get_top_per_country("XX", &date).await.unwrap_err()
+        ));
+        assert!(is_not_found(
+            &pv.get_top_by_country("xx.wikipedia", &date)
+                .await
+                .unwrap_err()
+        ));
+    }
+
     #[test]
     fn test_pageviews_timestamp() {
         let time_string = "2345123159";
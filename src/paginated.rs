@@ -0,0 +1,184 @@
+/// # Paginated
+/// The `Paginated` trait is implemented by tools whose results come in pages, e.g. via an offset or continuation parameter.
+/// A tool describes the request for one page (`page_request`), and parses a response into items and the cursor of the next page (`parse_page`).
+/// The provided `stream` (async) and `iter_blocking` methods fetch pages lazily, as the items are consumed,
+/// and stop after an optional overall limit.
+///
+/// ## Example
+/// ```ignore
+/// let search = WikiSearch::new(Site::from_wiki("enwiki").unwrap(), "Magnus Manske").with_limit(50);
+/// let ti = ToolsInterface::global();
+/// let titles: Vec<String> = search
+///     .stream(&ti, Some(200))
+///     .map_ok(|result| result.title)
+///     .try_collect()
+///     .await?;
+/// ```
+use std::collections::VecDeque;
+
+#[cfg(feature = "tokio")]
+use futures::stream::{BoxStream, StreamExt};
+
+use crate::{ToolRequest, ToolResponse, ToolsError, ToolsInterface};
+
+/// One page of results, and the cursor for the next page, if there is one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResultPage<T, C> {
+    pub items: Vec<T>,
+    pub next: Option<C>,
+}
+
+impl<T, C> ResultPage<T, C> {
+    /// A page after which there are no more results.
+    pub fn last(items: Vec<T>) -> Self {
+        Self { items, next: None }
+    }
+}
+
+pub trait Paginated: Sync {
+    /// A single result.
+    type Item: Send;
+
+    /// Identifies a page after the first one, e.g. an offset.
+    type Cursor: Send + Sync;
+
+    /// Describes the HTTP request for a page; `cursor` is `None` for the first page.
+    fn page_request(
+        &self,
+        ti: &ToolsInterface,
+        cursor: Option<&Self::Cursor>,
+    ) -> Result<ToolRequest, ToolsError>;
+
+    /// Parses the response for the page at `cursor`.
+    fn parse_page(
+        &self,
+        response: &ToolResponse,
+        cursor: Option<&Self::Cursor>,
+    ) -> Result<ResultPage<Self::Item, Self::Cursor>, ToolsError>;
+
+    #[cfg(feature = "tokio")]
+    /// Returns a stream of all results, across pages, up to `limit` results.
+    /// Pages are requested only when the results of the previous page have been consumed.
+    /// The stream ends after the first error.
+    fn stream<'a>(
+        &'a self,
+        ti: &'a ToolsInterface,
+        limit: Option<usize>,
+    ) -> BoxStream<'a, Result<Self::Item, ToolsError>>
+    where
+        Self: Sized,
+    {
+        futures::stream::unfold(PageState::new(limit), move |mut state| async move {
+            loop {
+                if let Some(next) = state.next_buffered() {
+                    return next.map(|item| (Ok(item), state));
+                }
+                let cursor = state.cursor.as_ref();
+                let page = match self.page_request(ti, cursor) {
+                    Ok(request) => match ti.execute_checked(&request).await {
+                        Ok(response) => self.parse_page(&response, cursor),
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = state.add_page(page) {
+                    return Some((Err(e), state));
+                }
+            }
+        })
+        .boxed()
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Returns an iterator over all results, across pages, up to `limit` results.
+    /// Pages are requested only when the results of the previous page have been consumed.
+    /// The iterator ends after the first error.
+    fn iter_blocking<'a>(
+        &'a self,
+        ti: &'a ToolsInterface,
+        limit: Option<usize>,
+    ) -> PaginatedIter<'a, Self>
+    where
+        Self: Sized,
+    {
+        PaginatedIter {
+            source: self,
+            ti,
+            state: PageState::new(limit),
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+/// Blocking iterator over the results of a `Paginated` tool, see `Paginated::iter_blocking`.
+pub struct PaginatedIter<'a, P: Paginated> {
+    source: &'a P,
+    ti: &'a ToolsInterface,
+    state: PageState<P::Item, P::Cursor>,
+}
+
+#[cfg(feature = "blocking")]
+impl<P: Paginated> Iterator for PaginatedIter<'_, P> {
+    type Item = Result<P::Item, ToolsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(next) = self.state.next_buffered() {
+                return next.map(Ok);
+            }
+            let cursor = self.state.cursor.as_ref();
+            let page = self
+                .source
+                .page_request(self.ti, cursor)
+                .and_then(|request| self.ti.execute_checked_blocking(&request))
+                .and_then(|response| self.source.parse_page(&response, cursor));
+            if let Err(e) = self.state.add_page(page) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Buffered results, and where to continue.
+struct PageState<T, C> {
+    buffer: VecDeque<T>,
+    cursor: Option<C>,
+    remaining: Option<usize>,
+    finished: bool,
+}
+
+impl<T, C> PageState<T, C> {
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            buffer: VecDeque::new(),
+            cursor: None,
+            remaining: limit,
+            finished: false,
+        }
+    }
+
+    /// Returns `Some(item)` for the next buffered result, `Some(None)` if iteration is over,
+    /// and `None` if the next page needs to be fetched.
+    fn next_buffered(&mut self) -> Option<Option<T>> {
+        if self.remaining == Some(0) {
+            return Some(None);
+        }
+        if let Some(item) = self.buffer.pop_front() {
+            self.remaining = self.remaining.map(|remaining| remaining - 1);
+            return Some(Some(item));
+        }
+        if self.finished {
+            return Some(None);
+        }
+        None
+    }
+
+    fn add_page(&mut self, page: Result<ResultPage<T, C>, ToolsError>) -> Result<(), ToolsError> {
+        let page = page.inspect_err(|_| self.finished = true)?;
+        // An empty page would request the same cursor forever
+        self.finished = page.next.is_none() || page.items.is_empty();
+        self.buffer.extend(page.items);
+        self.cursor = page.next;
+        Ok(())
+    }
+}
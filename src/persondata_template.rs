@@ -11,10 +11,12 @@
 /// ```
 use async_trait::async_trait;
 
-use crate::{Tool, ToolsError};
+use crate::{Endpoint, Tool, ToolRequest, ToolResponse, ToolsError, ToolsInterface};
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt};
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PersondataTemplatesOccOp {
     #[default]
     Equal,
@@ -32,7 +34,8 @@ impl fmt::Display for PersondataTemplatesOccOp {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PersondataTemplatesParamValueOp {
     #[default]
     Equal,
@@ -56,7 +59,8 @@ impl fmt::Display for PersondataTemplatesParamValueOp {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PersondataTemplatesParamNameOp {
     #[default]
     Equal,
@@ -82,7 +86,7 @@ impl fmt::Display for PersondataTemplatesParamNameOp {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PersondataTemplatesResult {
     article: String,
     usage_number: u32,
@@ -125,7 +129,8 @@ impl PersondataTemplatesResult {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PersondataTemplates {
     with_wl: bool,                                   // Mit Weiterleitungen
     tmpl: String,                                    // Name der Vorlage
@@ -231,8 +236,11 @@ impl PersondataTemplates {
         }
     }
 
-    fn generate_csv_url(&self) -> String {
-        let mut url = "https://persondata.toolforge.org/vorlagen/index.php?export=1&tzoffset=0&show_occ&show_param&show_value".to_string();
+    fn generate_csv_url(&self, ti: &ToolsInterface) -> String {
+        let mut url = format!(
+            "{}/index.php?export=1&tzoffset=0&show_occ&show_param&show_value",
+            ti.endpoint(Endpoint::PersondataTemplates)
+        );
 
         if !self.tmpl.is_empty() {
             url += &format!("&tmpl={}", self.tmpl);
@@ -294,39 +302,25 @@ impl PersondataTemplates {
 
 #[async_trait]
 impl Tool for PersondataTemplates {
-    #[cfg(feature = "blocking")]
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        let url = self.generate_csv_url();
-        let client = crate::ToolsInterface::blocking_client()?;
-        let response = client.get(&url).send()?;
+    type Output = Vec<PersondataTemplatesResult>;
 
-        let mut reader = csv::ReaderBuilder::new()
-            .delimiter(b';')
-            .has_headers(true)
-            .flexible(true)
-            .from_reader(response);
-        let headers = reader.headers()?.to_owned();
-
-        self.results = reader
-            .records()
-            .filter_map(|result| result.ok())
-            .map(|record| PersondataTemplatesResult::from_record(&headers, &record))
-            .collect();
-        Ok(())
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        Ok(ToolRequest::get(self.generate_csv_url(ti)))
     }
 
-    #[cfg(feature = "tokio")]
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = self.generate_csv_url();
-        let client = crate::ToolsInterface::tokio_client()?;
-        let response = client.get(&url).send().await?;
-        let body = response.text().await?;
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_text(&response.text())?;
+        Ok(self.results.clone())
+    }
+}
 
+impl PersondataTemplates {
+    fn set_from_text(&mut self, text: &str) -> Result<(), ToolsError> {
         let mut reader = csv::ReaderBuilder::new()
             .delimiter(b';')
             .has_headers(true)
             .flexible(true)
-            .from_reader(body.as_bytes());
+            .from_reader(text.as_bytes());
         let headers = reader.headers()?.to_owned();
 
         self.results = reader
@@ -341,6 +335,7 @@ impl Tool for PersondataTemplates {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::replay::Replay;
 
     #[test]
     fn test_persondata_templates_query() {
@@ -378,9 +373,15 @@ mod tests {
     fn get_persondata_template_blocking() {
         let mut query = PersondataTemplates::with_template("Roscher")
             .parameter_name_op("4", PersondataTemplatesParamNameOp::default());
-        query.run_blocking().unwrap();
+        let replay = Replay::start_blocking("persondata_roscher");
+        query.run_blocking_with(&replay).unwrap();
         let results = query.results();
-        assert!(results.len() > 2000);
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[2].article(), "Hermes (Mythologie)");
+        assert_eq!(
+            results[2].params().get(&4).map(|s| s.as_str()),
+            Some("2342")
+        );
     }
 
     #[cfg(feature = "tokio")]
@@ -388,8 +389,11 @@ mod tests {
     async fn get_persondata_template_async() {
         let mut query = PersondataTemplates::with_template("Roscher")
             .parameter_name_op("4", PersondataTemplatesParamNameOp::default());
-        query.run().await.unwrap();
+        let replay = Replay::start("persondata_roscher").await;
+        query.run_with(&replay).await.unwrap();
         let results = query.results();
-        assert!(results.len() > 2000);
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[3].article(), "Persephone");
+        assert_eq!(results[3].usage_number(), 2);
     }
 }
@@ -1,6 +1,6 @@
 /// # PetScan
 /// This module provides a wrapper around the PetScan tool.
-/// You can perform a PetScan query via a PSID.
+/// You can perform a PetScan query via a PSID, or build one with `PetScanQuery`.
 /// There are blocking and async methods available.
 ///
 /// ## Example
@@ -9,22 +9,31 @@
 /// ps.parameters_mut().push(("foo".to_string(), "bar".to_string())); // Override parameters from the PSID
 /// ps.get().await.unwrap();
 /// let page_titles = ps.pages.iter().map(|page| page.page_title).collect::<Vec<_>>();
+///
+/// // Without a PSID
+/// let mut ps = PetScan::from_query(PetScanQuery::new("en", "wikipedia").category("Physicists", 1));
+/// ps.run().await.unwrap();
 /// ```
 use std::collections::HashMap;
 
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, PetScanQuery, Site, Tool, ToolRequest, ToolResponse,
+    ToolsError, ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
+use mediawiki::title::Title;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
-#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct PetScanFileUsage {
     pub ns: i32,
     pub page: String,
     pub wiki: String,
 }
 
-#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct PetScanMetadata {
     // TODO defaultsort (fix JSON output upstream)
     #[serde(default)]
@@ -66,7 +75,7 @@ impl PetScanMetadata {
     }
 }
 
-#[derive(Debug, Default, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct PetScanPage {
     pub page_id: u32,
     pub page_latest: String,
@@ -79,6 +88,19 @@ pub struct PetScanPage {
     pub metadata: PetScanMetadata,
 }
 
+impl PetScanPage {
+    /// Returns the namespace-prefixed title, with spaces, e.g. "User talk:Foo".
+    /// Uses the namespace names that PetScan returns with the results (see `PetScan::namespaces`),
+    /// so no MediaWiki API call is needed. Returns `None` if the namespace is not known.
+    pub fn prefixed_title(&self, namespaces: &HashMap<i64, String>) -> Option<String> {
+        let title = Title::underscores_to_spaces(&self.page_title);
+        match namespaces.get(&self.page_namespace)?.as_str() {
+            "" => Some(title),
+            ns => Some(format!("{}:{title}", Title::underscores_to_spaces(ns))),
+        }
+    }
+}
+
 impl From<PetScanPage> for mediawiki::title::Title {
     fn from(val: PetScanPage) -> Self {
         let title_with_spaces = mediawiki::title::Title::underscores_to_spaces(&val.page_title);
@@ -86,12 +108,13 @@ impl From<PetScanPage> for mediawiki::title::Title {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PetScan {
-    psid: u32,
+    psid: Option<u32>,
     parameters: Vec<(String, String)>,
     pages: Vec<PetScanPage>,
-    namespaces: HashMap<i32, String>,
+    namespaces: HashMap<i64, String>,
     query: Option<String>,
     wiki: Option<String>,
     status: Option<String>,
@@ -101,18 +124,31 @@ impl PetScan {
     /// Create a new PetScan query with a PSID.
     pub fn new(psid: u32) -> Self {
         Self {
-            psid,
+            psid: Some(psid),
             ..Default::default()
         }
     }
 
+    /// Create a new PetScan query without a PSID, from the parameters of `query`.
+    pub fn from_query(query: PetScanQuery) -> Self {
+        Self {
+            parameters: query.parameters(),
+            ..Default::default()
+        }
+    }
+
+    /// Get the PSID, if the query is based on one.
+    pub fn psid(&self) -> Option<u32> {
+        self.psid
+    }
+
     /// Get the mutable parameters for the future PetScan query.
     /// You can override the parameters from the PSID this way.
     pub fn parameters_mut(&mut self) -> &mut Vec<(String, String)> {
         &mut self.parameters
     }
 
-    /// Get the namespaces from the PetScan query.
+    /// Get the pages from the PetScan query.
     pub fn pages(&self) -> &[PetScanPage] {
         &self.pages
     }
@@ -127,102 +163,131 @@ impl PetScan {
         self.query.as_ref()
     }
 
-    pub async fn as_json(&self) -> Value {
-        let site = Site::from_wiki(self.wiki().expect("No wiki in PetScan result")).unwrap();
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.pages()
-                .iter()
-                .map(|page| (FancyTitle::new(&page.page_title, page.page_namespace, &api).to_json(),page))
-                .map(|(mut j,page)| {
-                    j["id"] = page.page_id.into(); // TODO consistent with other tools?
-                    j["len"] = page.page_len.into(); // TODO consistent with other tools?
-                    j["timestamp"] = json!(page.page_latest); // TODO consistent with other tools?
-                    j["metadata"] = json!(page.metadata);
-                    j["giu"] = json!(page.giu); // Global image usage
-                    j
-                })
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
+    /// Get the namespace names of the (main) wiki, by namespace ID, as returned by PetScan.
+    pub fn namespaces(&self) -> &HashMap<i64, String> {
+        &self.namespaces
+    }
+
+    /// Get the status returned by PetScan, "OK" for a successful query.
+    pub fn status(&self) -> Option<&String> {
+        self.status.as_ref()
     }
 }
 
 #[async_trait]
 impl Tool for PetScan {
-    #[cfg(feature = "blocking")]
-    /// Perform a blocking PetScan query.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        let url = format!(
-            "https://petscan.wmflabs.org/?psid={psid}&format=json&output_compatability=quick-intersection",
-            psid = self.psid
-        );
-        let client = crate::ToolsInterface::blocking_client()?;
-        let j: Value = client.get(&url).query(&self.parameters).send()?.json()?;
-        self.set_from_json(j)
+    type Output = Vec<PetScanPage>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        Ok(self.request_with_output(ti, "format=json&output_compatability=quick-intersection"))
     }
 
-    #[cfg(feature = "tokio")]
-    /// Get the PetScan query asynchronously.
-    async fn run(&mut self) -> Result<(), ToolsError> {
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.pages.clone())
+    }
+}
+
+impl PetScan {
+    /// Builds the request, with `output` selecting the output format (URL parameters).
+    pub(crate) fn request_with_output(&self, ti: &ToolsInterface, output: &str) -> ToolRequest {
+        // Without a PSID, `doit` makes PetScan run the query from the parameters alone
+        let source = match self.psid {
+            Some(psid) => format!("psid={psid}"),
+            None => "doit=1".to_string(),
+        };
         let url = format!(
-            "https://petscan.wmflabs.org/?psid={psid}&format=json&output_compatability=quick-intersection",
-            psid = self.psid
+            "{base}/?{source}&{output}",
+            base = ti.endpoint(Endpoint::PetScan),
         );
-        let client = crate::ToolsInterface::tokio_client()?;
-        let j = client
-            .get(&url)
-            .query(&self.parameters)
-            .send()
-            .await?
-            .json()
-            .await?;
-        self.set_from_json(j)
+        ToolRequest::get(url).query(&self.parameters)
     }
 
-    fn set_from_json(&mut self, json: Value) -> Result<(), ToolsError> {
+    pub(crate) fn set_from_json(&mut self, json: Value) -> Result<(), ToolsError> {
         self.status = json["status"].as_str().map(|s| s.to_string());
         if self.status != Some("OK".to_string()) {
-            return Err(ToolsError::Tool(format!(
-                "PetScan status is not OK: {:?}",
-                self.status
-            )));
+            return Err(ToolsError::tool_status(
+                "PetScan",
+                self.status.to_owned().unwrap_or_default(),
+            ));
         }
         self.query = json["query"].as_str().map(|s| s.to_string());
-        self.namespaces = json["namespaces"]
-            .as_object()
-            .ok_or(ToolsError::Json("['namespaces'] has no object".into()))?
-            .iter()
-            .map(|(k, v)| (k.parse().unwrap(), v.as_str().unwrap().to_string()))
-            .collect();
+        self.namespaces = Self::parse_namespaces(&json["namespaces"])?;
         self.wiki = json["wiki"].as_str().map(|s| s.to_string());
-        for page_json in json["pages"]
+        self.pages = json["pages"]
             .as_array()
-            .ok_or(ToolsError::Json("['pages'] has no array".into()))?
-        {
-            let page: PetScanPage = serde_json::from_value(page_json.clone())?;
-            self.pages.push(page);
-        }
+            .ok_or_else(|| ToolsError::parse("PetScan", "pages", "not an array"))?
+            .iter()
+            .map(|page_json| serde_json::from_value(page_json.clone()))
+            .collect::<Result<_, _>>()?;
         Ok(())
     }
+
+    /// Parses the namespace object, e.g. `{"0":"","1":"Talk"}`.
+    fn parse_namespaces(json: &Value) -> Result<HashMap<i64, String>, ToolsError> {
+        json.as_object()
+            .ok_or_else(|| ToolsError::parse("PetScan", "namespaces", "not an object"))?
+            .iter()
+            .map(|(k, v)| {
+                let id = k.parse().map_err(|_| {
+                    ToolsError::parse("PetScan", "namespaces", format!("invalid ID '{k}'"))
+                })?;
+                let name = v.as_str().ok_or_else(|| {
+                    ToolsError::parse("PetScan", "namespaces", format!("no name for ID {id}"))
+                })?;
+                Ok((id, name.to_string()))
+            })
+            .collect()
+    }
+}
+
+impl PageListSource for PetScan {
+    fn page_list_site(&self) -> Option<Site> {
+        Site::from_wiki(self.wiki.as_deref()?)
+    }
+
+    fn page_list_with(&self, _namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let site = self
+            .page_list_site()
+            .ok_or_else(|| ToolsError::parse("PetScan", "wiki", "missing or unknown"))?;
+        let pages = self
+            .pages
+            .iter()
+            .map(|page| {
+                let title = Title::new(&page.page_title, page.page_namespace);
+                let meta = PageMeta {
+                    page_id: Some(page.page_id as u64),
+                    size: Some(page.page_len as u64),
+                    timestamp: Some(page.page_latest.to_owned()),
+                    ..Default::default()
+                }
+                .with_extra("metadata", json!(page.metadata))
+                .with_extra("giu", json!(page.giu)); // Global image usage
+                Page::new(title, meta)
+            })
+            .collect();
+        Ok(PageList::new(site, pages))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::replay::Replay;
 
     #[test]
     fn test_petscan_new() {
         let ps = PetScan::new(123);
-        assert_eq!(ps.psid, 123);
+        assert_eq!(ps.psid, Some(123));
         assert_eq!(ps.pages, vec![]);
     }
 
     #[cfg(feature = "blocking")]
     #[test]
     fn test_petscan_get_blocking() {
+        let replay = Replay::start_blocking("petscan_25951472");
         let mut ps = PetScan::new(25951472);
-        ps.run_blocking().unwrap();
+        ps.run_blocking_with(&replay).unwrap();
         assert_eq!(ps.pages.len(), 1);
         assert_eq!(ps.pages[0].page_id, 3361346);
         assert_eq!(ps.pages[0].page_title, "Magnus_Manske");
@@ -231,18 +296,34 @@ mod tests {
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_pagepile_get_async() {
+        let replay = Replay::start("petscan_25951472").await;
         let mut ps = PetScan::new(25951472);
-        ps.run().await.unwrap();
+        ps.run_with(&replay).await.unwrap();
+        assert_eq!(ps.status().map(|s| s.as_str()), Some("OK"));
+        assert_eq!(ps.namespaces().get(&0).map(|s| s.as_str()), Some(""));
         assert_eq!(ps.pages.len(), 1);
         assert_eq!(ps.pages[0].page_id, 3361346);
         assert_eq!(ps.pages[0].page_title, "Magnus_Manske");
     }
 
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_petscan_run_twice() {
+        let replay = Replay::start("petscan_25951472").await;
+        let mut ps = PetScan::new(25951472);
+        let first = ps.run_with(&replay).await.unwrap();
+        let second = ps.run_with(&replay).await.unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(second, first);
+        assert_eq!(ps.pages().len(), 1);
+    }
+
     #[cfg(feature = "blocking")]
     #[test]
     fn test_petscan_get_blocking_file() {
+        let replay = Replay::start_blocking("petscan_28348161");
         let mut ps = PetScan::new(28348161);
-        ps.run_blocking().unwrap();
+        ps.run_blocking_with(&replay).unwrap();
         let expected_giui = PetScanFileUsage {
             ns: 0,
             page: "St._Laurentius_(Wald-Michelbach)".to_string(),
@@ -264,8 +345,9 @@ mod tests {
     #[cfg(feature = "blocking")]
     #[test]
     fn test_petscan_get_blocking_metadata() {
+        let replay = Replay::start_blocking("petscan_28348714");
         let mut ps = PetScan::new(28348714);
-        ps.run_blocking().unwrap();
+        ps.run_blocking_with(&replay).unwrap();
         assert_eq!(ps.pages[0].page_id, 12115738);
         assert_eq!(ps.pages[0].page_title, "St._Laurentius_(Wald-Michelbach)");
         assert_eq!(
@@ -279,6 +361,91 @@ mod tests {
         assert_eq!(ps.pages[0].metadata.wikidata, "Q110825193");
     }
 
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_petscan_from_query() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .and(query_param("doit", "1"))
+            .and(query_param("format", "json"))
+            .and(query_param("language", "en"))
+            .and(query_param("categories", "Living people|0"))
+            .and(query_param("templates_yes", "Infobox scientist"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"namespaces":{"0":""},"status":"OK","query":"","wiki":"enwiki","pages":[{"page_id":3361346,"page_latest":"1252041186","page_len":10211,"page_namespace":0,"page_title":"Magnus_Manske"}]})))
+            .mount(&mock_server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::PetScan, mock_server.uri())
+            .build()
+            .unwrap();
+        let query = PetScanQuery::new("en", "wikipedia")
+            .category("Living people", 0)
+            .template("Infobox scientist");
+        let mut ps = PetScan::from_query(query);
+        assert_eq!(ps.psid(), None);
+        ps.run_with(&ti).await.unwrap();
+        assert_eq!(ps.pages.len(), 1);
+        assert_eq!(ps.pages[0].page_title, "Magnus_Manske");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_petscan_query_same_as_psid() {
+        let replay = Replay::start("petscan_28348714_query").await;
+        let mut from_psid = PetScan::new(28348714);
+        from_psid.run_with(&replay).await.unwrap();
+        let query = PetScanQuery::new("de", "wikipedia")
+            .category("Kirchengebäude im Kreis Bergstraße", 0)
+            .links_to("Wald-Michelbach")
+            .linked_from("Liste der Kulturdenkmäler in Wald-Michelbach");
+        let parameters = query.parameters();
+        assert!(parameters.contains(&("links_to_all".to_string(), "Wald-Michelbach".to_string())));
+        assert!(parameters.contains(&(
+            "outlinks_yes".to_string(),
+            "Liste der Kulturdenkmäler in Wald-Michelbach".to_string()
+        )));
+        let mut from_query = PetScan::from_query(query);
+        from_query.run_with(&replay).await.unwrap();
+        assert_eq!(from_query.pages().len(), 1);
+        assert_eq!(from_query.pages(), from_psid.pages());
+    }
+
+    #[test]
+    fn test_petscan_parse_namespaces() {
+        let namespaces = PetScan::parse_namespaces(&json!({"0":"","3":"User_talk"})).unwrap();
+        let page = PetScanPage {
+            page_namespace: 3,
+            page_title: "Magnus_Manske".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            page.prefixed_title(&namespaces).as_deref(),
+            Some("User talk:Magnus Manske")
+        );
+        let page = PetScanPage {
+            page_title: "Foo_bar".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(page.prefixed_title(&namespaces).as_deref(), Some("Foo bar"));
+        let page = PetScanPage {
+            page_namespace: 14,
+            ..page
+        };
+        assert_eq!(page.prefixed_title(&namespaces), None);
+
+        assert!(PetScan::parse_namespaces(&json!({"x":""})).is_err());
+        assert!(PetScan::parse_namespaces(&json!({"0":null})).is_err());
+        assert!(PetScan::parse_namespaces(&json!([])).is_err());
+
+        let mut ps = PetScan::default();
+        let result = ps.set_from_json(json!({"status":"OK","namespaces":{"zero":""},"pages":[]}));
+        assert!(matches!(result, Err(ToolsError::Parse { .. })));
+    }
+
     #[test]
     fn test_petscan_into_title() {
         let ps = PetScanPage {
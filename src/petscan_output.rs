@@ -0,0 +1,332 @@
+/// # PetScan output
+/// Besides the page list used by `PetScan`, PetScan can return its results in other formats.
+/// `PetScanExport` runs a PetScan query (by PSID or `PetScanQuery`) in one of these formats,
+/// and parses the result into a `PetScanOutput`:
+/// - the Wikidata items of the result pages
+/// - a new PagePile with the result pages, returned by ID, so other tools can use it directly
+/// - a wikitext table
+/// - a TSV table
+///
+/// ## Example
+/// ```ignore
+/// let mut export = PetScanExport::new(PetScan::new(12345), PetScanFormat::PagePile);
+/// let output = export.run().await?;
+/// let mut pp = output.pagepile().unwrap(); // The new PagePile
+/// pp.run().await?;
+/// ```
+use crate::{PagePile, PetScan, Tool, ToolRequest, ToolResponse, ToolsError, ToolsInterface};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// The output format of a `PetScanExport`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PetScanFormat {
+    /// The Wikidata items of the result pages.
+    #[default]
+    WikidataItems,
+    /// A new PagePile with the result pages.
+    PagePile,
+    /// A wikitext table.
+    Wikitext,
+    /// A table with tab-separated values.
+    Tsv,
+}
+
+impl PetScanFormat {
+    /// The URL parameters selecting the format.
+    fn parameters(&self) -> &str {
+        match self {
+            // Using Wikidata as the common wiki converts the results to their items
+            PetScanFormat::WikidataItems => {
+                "format=json&output_compatability=quick-intersection&common_wiki=wikidata"
+            }
+            PetScanFormat::PagePile => "format=pagepile",
+            PetScanFormat::Wikitext => "format=wiki",
+            PetScanFormat::Tsv => "format=tsv",
+        }
+    }
+}
+
+/// A table from the wikitext or TSV output.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PetScanTable {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl PetScanTable {
+    /// Parses a table with tab-separated values, and a header row.
+    pub fn from_tsv(text: &str) -> Result<Self, ToolsError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(true)
+            .flexible(true)
+            .quoting(false)
+            .from_reader(text.as_bytes());
+        let header = reader.headers()?.iter().map(|s| s.to_string()).collect();
+        let rows = reader
+            .records()
+            .map(|record| Ok(record?.iter().map(|s| s.to_string()).collect()))
+            .collect::<Result<_, ToolsError>>()?;
+        Ok(Self { header, rows })
+    }
+
+    /// Parses the first wikitext table (`{| ... |}`) in `text`.
+    /// Cells can be on one line (separated by `||`) or on separate lines.
+    pub fn from_wikitext(text: &str) -> Result<Self, ToolsError> {
+        let mut lines = text
+            .lines()
+            .map(|line| line.trim())
+            .skip_while(|line| !line.starts_with("{|"));
+        if lines.next().is_none() {
+            return Err(ToolsError::parse("PetScan", "wikitext", "no table found"));
+        }
+        let mut table = Self::default();
+        let mut row: Vec<String> = vec![];
+        for line in lines {
+            if line.starts_with("|}") {
+                break;
+            } else if line.starts_with("|-") {
+                table.push_row(&mut row);
+            } else if let Some(cells) = line.strip_prefix('!') {
+                table.header.extend(Self::split_cells(cells, "!!"));
+            } else if let Some(cells) = line.strip_prefix('|') {
+                row.extend(Self::split_cells(cells, "||"));
+            }
+        }
+        table.push_row(&mut row);
+        Ok(table)
+    }
+
+    /// Returns the values of the column with this header.
+    pub fn column(&self, name: &str) -> Option<Vec<&str>> {
+        let index = self.header.iter().position(|h| h == name)?;
+        let values = self
+            .rows
+            .iter()
+            .map(|row| row.get(index).map(|s| s.as_str()).unwrap_or_default())
+            .collect();
+        Some(values)
+    }
+
+    fn split_cells(cells: &str, separator: &str) -> Vec<String> {
+        cells
+            .split(separator)
+            .map(|s| s.trim().to_string())
+            .collect()
+    }
+
+    fn push_row(&mut self, row: &mut Vec<String>) {
+        if !row.is_empty() {
+            self.rows.push(std::mem::take(row));
+        }
+    }
+}
+
+/// The parsed result of a `PetScanExport`, depending on the `PetScanFormat`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PetScanOutput {
+    WikidataItems(Vec<String>),
+    PagePile(u32),
+    Wikitext {
+        wikitext: String,
+        table: PetScanTable,
+    },
+    Tsv(PetScanTable),
+}
+
+impl PetScanOutput {
+    /// Returns the new PagePile, for `PetScanFormat::PagePile`.
+    pub fn pagepile(&self) -> Option<PagePile> {
+        match self {
+            PetScanOutput::PagePile(id) => Some(PagePile::new(*id)),
+            _ => None,
+        }
+    }
+
+    /// Returns the table, for `PetScanFormat::Wikitext` and `PetScanFormat::Tsv`.
+    pub fn table(&self) -> Option<&PetScanTable> {
+        match self {
+            PetScanOutput::Wikitext { table, .. } | PetScanOutput::Tsv(table) => Some(table),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PetScanExport {
+    petscan: PetScan,
+    format: PetScanFormat,
+    output: Option<PetScanOutput>,
+}
+
+impl PetScanExport {
+    /// Runs the query of `petscan` in another output format.
+    pub fn new(petscan: PetScan, format: PetScanFormat) -> Self {
+        Self {
+            petscan,
+            format,
+            output: None,
+        }
+    }
+
+    pub fn format(&self) -> &PetScanFormat {
+        &self.format
+    }
+
+    /// Returns the output of the last run.
+    pub fn output(&self) -> Option<&PetScanOutput> {
+        self.output.as_ref()
+    }
+
+    fn parse_wikidata_items(response: &ToolResponse) -> Result<Vec<String>, ToolsError> {
+        let mut petscan = PetScan::default();
+        petscan.set_from_json(response.json()?)?;
+        let items = petscan
+            .pages()
+            .iter()
+            .filter_map(|page| match page.metadata.wikidata.is_empty() {
+                false => Some(page.metadata.wikidata.to_owned()),
+                true if page.page_namespace == 0 => Some(page.page_title.to_owned()),
+                true => None,
+            })
+            .collect();
+        Ok(items)
+    }
+
+    /// PetScan redirects to the new PagePile, so the ID is taken from the redirect target,
+    /// or from the page it leads to.
+    fn parse_pagepile_id(response: &ToolResponse) -> Result<u32, ToolsError> {
+        lazy_static! {
+            static ref RE_PAGEPILE_ID: Regex =
+                Regex::new(r"[?&](?:id|pile)=(\d+)").expect("Regex pattern should be valid");
+        }
+        let body = response.text();
+        [response.header("location"), response.url(), Some(&body)]
+            .into_iter()
+            .flatten()
+            .find_map(|text| RE_PAGEPILE_ID.captures(text)?.get(1)?.as_str().parse().ok())
+            .ok_or_else(|| ToolsError::parse("PetScan", "pagepile", "no PagePile ID found"))
+    }
+}
+
+#[async_trait]
+impl Tool for PetScanExport {
+    type Output = PetScanOutput;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        Ok(self
+            .petscan
+            .request_with_output(ti, self.format.parameters()))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        let output = match self.format {
+            PetScanFormat::WikidataItems => {
+                PetScanOutput::WikidataItems(Self::parse_wikidata_items(response)?)
+            }
+            PetScanFormat::PagePile => PetScanOutput::PagePile(Self::parse_pagepile_id(response)?),
+            PetScanFormat::Wikitext => {
+                let wikitext = response.text();
+                let table = PetScanTable::from_wikitext(&wikitext)?;
+                PetScanOutput::Wikitext { wikitext, table }
+            }
+            PetScanFormat::Tsv => PetScanOutput::Tsv(PetScanTable::from_tsv(&response.text())?),
+        };
+        self.output = Some(output.clone());
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::{Endpoint, PetScanQuery};
+
+    #[test]
+    fn test_table_from_wikitext() {
+        let wikitext = "== PetScan ==\n{| class=\"wikitable sortable\"\n! Title !! Page ID !! Namespace\n|-\n| [[Magnus Manske|Magnus]] || 3361346 || 0\n|-\n| [[Foo]]\n| 123\n| 0\n|}\n";
+        let table = PetScanTable::from_wikitext(wikitext).unwrap();
+        assert_eq!(table.header, vec!["Title", "Page ID", "Namespace"]);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0][0], "[[Magnus Manske|Magnus]]");
+        assert_eq!(table.column("Page ID"), Some(vec!["3361346", "123"]));
+        assert!(PetScanTable::from_wikitext("no table").is_err());
+    }
+
+    #[test]
+    fn test_table_from_tsv() {
+        let table =
+            PetScanTable::from_tsv("title\tpage_id\nMagnus_Manske\t3361346\nFoo\"\t123\n").unwrap();
+        assert_eq!(table.header, vec!["title", "page_id"]);
+        assert_eq!(table.column("title"), Some(vec!["Magnus_Manske", "Foo\""]));
+        assert_eq!(table.column("nope"), None);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_petscan_export_pagepile() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .and(query_param("psid", "25951472"))
+            .and(query_param("format", "pagepile"))
+            .respond_with(ResponseTemplate::new(302).insert_header(
+                "Location",
+                format!("{}/api.php?id=54321&action=get_data", mock_server.uri()),
+            ))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api.php"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html></html>"))
+            .mount(&mock_server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::PetScan, mock_server.uri())
+            .build()
+            .unwrap();
+        let mut export = PetScanExport::new(PetScan::new(25951472), PetScanFormat::PagePile);
+        let output = export.run_with(&ti).await.unwrap();
+        assert_eq!(output, PetScanOutput::PagePile(54321));
+        assert_eq!(output.pagepile(), Some(PagePile::new(54321)));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_petscan_export_wikidata_items() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .and(query_param("doit", "1"))
+            .and(query_param("common_wiki", "wikidata"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"namespaces":{"0":""},"status":"OK","query":"","wiki":"wikidatawiki","pages":[{"page_id":13520818,"page_latest":"2258433325","page_len":1234,"page_namespace":0,"page_title":"Q13520818"}]})))
+            .mount(&mock_server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::PetScan, mock_server.uri())
+            .build()
+            .unwrap();
+        let petscan = PetScan::from_query(PetScanQuery::new("en", "wikipedia").category("Foo", 0));
+        let mut export = PetScanExport::new(petscan, PetScanFormat::WikidataItems);
+        let output = export.run_with(&ti).await.unwrap();
+        assert_eq!(
+            output,
+            PetScanOutput::WikidataItems(vec!["Q13520818".to_string()])
+        );
+        assert_eq!(export.output(), Some(&output));
+    }
+}
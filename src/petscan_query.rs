@@ -0,0 +1,360 @@
+/// # PetScan query
+/// `PetScanQuery` builds a PetScan query from typed parameters, so no stored PSID is needed.
+/// It covers the most common parameters: wiki, categories (with depth and combination),
+/// templates, namespaces, links, Wikidata filters, sorting, and the output limit.
+/// Use `PetScan::from_query` to run it; the results are the same `PetScanPage`s as for a PSID.
+///
+/// ## Example
+/// ```ignore
+/// let query = PetScanQuery::new("de", "wikipedia")
+///     .category("Biologie", 2)
+///     .category("Deutscher", 0)
+///     .combination(PetScanCombination::Subset)
+///     .negative_template("Begriffsklärung")
+///     .namespace(0)
+///     .wikidata(PetScanWikidata::Without)
+///     .sort(PetScanSort::Size, true)
+///     .output_limit(100);
+/// let mut ps = PetScan::from_query(query);
+/// ps.run().await?;
+/// ```
+use crate::Site;
+use serde::{Deserialize, Serialize};
+
+/// How the pages of several categories are combined.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PetScanCombination {
+    /// Pages in all categories.
+    #[default]
+    Subset,
+    /// Pages in any category.
+    Union,
+}
+
+impl PetScanCombination {
+    fn as_str(&self) -> &str {
+        match self {
+            PetScanCombination::Subset => "subset",
+            PetScanCombination::Union => "union",
+        }
+    }
+}
+
+/// Filters pages by whether they have a Wikidata item.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PetScanWikidata {
+    #[default]
+    Any,
+    With,
+    Without,
+}
+
+impl PetScanWikidata {
+    fn as_str(&self) -> &str {
+        match self {
+            PetScanWikidata::Any => "any",
+            PetScanWikidata::With => "with",
+            PetScanWikidata::Without => "without",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PetScanSort {
+    #[default]
+    None,
+    Title,
+    NsTitle,
+    Size,
+    Date,
+    IncomingLinks,
+    FileCount,
+    Random,
+    DefaultSort,
+}
+
+impl PetScanSort {
+    fn as_str(&self) -> &str {
+        match self {
+            PetScanSort::None => "none",
+            PetScanSort::Title => "title",
+            PetScanSort::NsTitle => "ns_title",
+            PetScanSort::Size => "size",
+            PetScanSort::Date => "date",
+            PetScanSort::IncomingLinks => "incoming_links",
+            PetScanSort::FileCount => "filecount",
+            PetScanSort::Random => "random",
+            PetScanSort::DefaultSort => "defaultsort",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PetScanQuery {
+    language: String,
+    project: String,
+    categories: Vec<(String, u32)>,
+    combination: PetScanCombination,
+    negative_categories: Vec<(String, u32)>,
+    templates_yes: Vec<String>,
+    templates_any: Vec<String>,
+    templates_no: Vec<String>,
+    namespaces: Vec<i64>,
+    outlinks_yes: Vec<String>,
+    outlinks_any: Vec<String>,
+    outlinks_no: Vec<String>,
+    links_to_all: Vec<String>,
+    links_to_any: Vec<String>,
+    links_to_no: Vec<String>,
+    wikidata: PetScanWikidata,
+    sort: PetScanSort,
+    sort_descending: bool,
+    output_limit: Option<usize>,
+}
+
+impl PetScanQuery {
+    /// Creates a new query for the given language and project, e.g. ("de", "wikipedia").
+    pub fn new(language: &str, project: &str) -> Self {
+        Self {
+            language: language.to_string(),
+            project: project.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new query for the given site.
+    pub fn from_site(site: &Site) -> Self {
+        Self::new(site.language(), site.project())
+    }
+
+    /// Adds a category, without namespace prefix, including subcategories up to `depth`.
+    pub fn category(mut self, category: &str, depth: u32) -> Self {
+        self.categories.push((category.to_string(), depth));
+        self
+    }
+
+    /// Sets how the pages of several categories are combined. Default is `Subset`.
+    pub fn combination(mut self, combination: PetScanCombination) -> Self {
+        self.combination = combination;
+        self
+    }
+
+    /// Excludes pages in a category (and its subcategories up to `depth`).
+    pub fn negative_category(mut self, category: &str, depth: u32) -> Self {
+        self.negative_categories.push((category.to_string(), depth));
+        self
+    }
+
+    /// Pages must use this template, given without namespace prefix.
+    pub fn template(mut self, template: &str) -> Self {
+        self.templates_yes.push(template.to_string());
+        self
+    }
+
+    /// Pages must use at least one of the templates added this way.
+    pub fn template_any(mut self, template: &str) -> Self {
+        self.templates_any.push(template.to_string());
+        self
+    }
+
+    /// Pages must not use this template.
+    pub fn negative_template(mut self, template: &str) -> Self {
+        self.templates_no.push(template.to_string());
+        self
+    }
+
+    /// Adds a namespace to search in. Without any, PetScan uses the main namespace.
+    pub fn namespace(mut self, namespace_id: i64) -> Self {
+        self.namespaces.push(namespace_id);
+        self
+    }
+
+    /// Pages must link to this page.
+    pub fn links_to(mut self, title: &str) -> Self {
+        self.links_to_all.push(title.to_string());
+        self
+    }
+
+    /// Pages must link to at least one of the pages added this way.
+    pub fn links_to_any(mut self, title: &str) -> Self {
+        self.links_to_any.push(title.to_string());
+        self
+    }
+
+    /// Pages must not link to this page.
+    pub fn not_links_to(mut self, title: &str) -> Self {
+        self.links_to_no.push(title.to_string());
+        self
+    }
+
+    /// Pages must be linked from this page.
+    pub fn linked_from(mut self, title: &str) -> Self {
+        self.outlinks_yes.push(title.to_string());
+        self
+    }
+
+    /// Pages must be linked from at least one of the pages added this way.
+    pub fn linked_from_any(mut self, title: &str) -> Self {
+        self.outlinks_any.push(title.to_string());
+        self
+    }
+
+    /// Pages must not be linked from this page.
+    pub fn not_linked_from(mut self, title: &str) -> Self {
+        self.outlinks_no.push(title.to_string());
+        self
+    }
+
+    /// Filters pages by whether they have a Wikidata item.
+    pub fn wikidata(mut self, wikidata: PetScanWikidata) -> Self {
+        self.wikidata = wikidata;
+        self
+    }
+
+    /// Sets the sort order of the results.
+    pub fn sort(mut self, sort: PetScanSort, descending: bool) -> Self {
+        self.sort = sort;
+        self.sort_descending = descending;
+        self
+    }
+
+    /// Returns at most this many pages.
+    pub fn output_limit(mut self, output_limit: usize) -> Self {
+        self.output_limit = Some(output_limit);
+        self
+    }
+
+    /// Returns the query as PetScan URL parameters.
+    pub fn parameters(&self) -> Vec<(String, String)> {
+        let mut parameters: Vec<(String, String)> = vec![
+            ("language".to_string(), self.language.to_owned()),
+            ("project".to_string(), self.project.to_owned()),
+        ];
+        let mut add_lines = |key: &str, lines: Vec<String>| {
+            if !lines.is_empty() {
+                parameters.push((key.to_string(), lines.join("\n")));
+            }
+        };
+        add_lines("categories", Self::with_depths(&self.categories));
+        add_lines("negcats", Self::with_depths(&self.negative_categories));
+        add_lines("templates_yes", self.templates_yes.to_owned());
+        add_lines("templates_any", self.templates_any.to_owned());
+        add_lines("templates_no", self.templates_no.to_owned());
+        add_lines("outlinks_yes", self.outlinks_yes.to_owned());
+        add_lines("outlinks_any", self.outlinks_any.to_owned());
+        add_lines("outlinks_no", self.outlinks_no.to_owned());
+        add_lines("links_to_all", self.links_to_all.to_owned());
+        add_lines("links_to_any", self.links_to_any.to_owned());
+        add_lines("links_to_no", self.links_to_no.to_owned());
+        if !self.categories.is_empty() {
+            parameters.push((
+                "combination".to_string(),
+                self.combination.as_str().to_string(),
+            ));
+        }
+        for namespace_id in &self.namespaces {
+            parameters.push((format!("ns[{namespace_id}]"), "1".to_string()));
+        }
+        parameters.push((
+            "wikidata_item".to_string(),
+            self.wikidata.as_str().to_string(),
+        ));
+        parameters.push(("sortby".to_string(), self.sort.as_str().to_string()));
+        let sortorder = match self.sort_descending {
+            true => "descending",
+            false => "ascending",
+        };
+        parameters.push(("sortorder".to_string(), sortorder.to_string()));
+        if let Some(output_limit) = self.output_limit {
+            parameters.push(("output_limit".to_string(), output_limit.to_string()));
+        }
+        parameters
+    }
+
+    /// PetScan takes a depth per category as "Category|depth".
+    fn with_depths(categories: &[(String, u32)]) -> Vec<String> {
+        categories
+            .iter()
+            .map(|(category, depth)| format!("{category}|{depth}"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameters() {
+        let query = PetScanQuery::new("de", "wikipedia")
+            .category("Biologie", 2)
+            .category("Deutscher", 0)
+            .combination(PetScanCombination::Union)
+            .negative_template("Begriffsklärung")
+            .namespace(0)
+            .namespace(14)
+            .links_to("Zelle")
+            .linked_from("Biologie")
+            .wikidata(PetScanWikidata::Without)
+            .sort(PetScanSort::Size, true)
+            .output_limit(100);
+        let parameters = query.parameters();
+        let get = |key: &str| {
+            parameters
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(get("language"), Some("de"));
+        assert_eq!(get("categories"), Some("Biologie|2\nDeutscher|0"));
+        assert_eq!(get("combination"), Some("union"));
+        assert_eq!(get("templates_no"), Some("Begriffsklärung"));
+        assert_eq!(get("templates_yes"), None);
+        assert_eq!(get("ns[0]"), Some("1"));
+        assert_eq!(get("ns[14]"), Some("1"));
+        assert_eq!(get("links_to_all"), Some("Zelle"));
+        assert_eq!(get("outlinks_yes"), Some("Biologie"));
+        assert_eq!(get("wikidata_item"), Some("without"));
+        assert_eq!(get("sortby"), Some("size"));
+        assert_eq!(get("sortorder"), Some("descending"));
+        assert_eq!(get("output_limit"), Some("100"));
+    }
+
+    #[test]
+    fn test_link_parameters() {
+        let query = PetScanQuery::new("de", "wikipedia")
+            .links_to("A")
+            .links_to_any("B")
+            .not_links_to("C")
+            .linked_from("D")
+            .linked_from_any("E")
+            .not_linked_from("F");
+        let parameters: Vec<_> = query
+            .parameters()
+            .into_iter()
+            .filter(|(k, _)| k.starts_with("links_to") || k.starts_with("outlinks"))
+            .collect();
+        let expected = [
+            ("outlinks_yes", "D"),
+            ("outlinks_any", "E"),
+            ("outlinks_no", "F"),
+            ("links_to_all", "A"),
+            ("links_to_any", "B"),
+            ("links_to_no", "C"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        assert_eq!(parameters, expected);
+    }
+
+    #[test]
+    fn test_from_site() {
+        let site = Site::from_wiki("enwikisource").unwrap();
+        let query = PetScanQuery::from_site(&site);
+        assert_eq!(query.language, "en");
+        assert_eq!(query.project, "wikisource");
+    }
+}
@@ -18,9 +18,13 @@
 use async_trait::async_trait;
 use serde_json::Value;
 
-use crate::{Tool, ToolsError};
+use crate::{
+    Endpoint, Paginated, ResultPage, Tool, ToolRequest, ToolResponse, ToolsError, ToolsInterface,
+};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Quarry {
     id: u64,
     columns: Vec<String>,
@@ -59,83 +63,110 @@ impl Quarry {
 
 #[async_trait]
 impl Tool for Quarry {
-    #[cfg(feature = "blocking")]
-    /// Download the latest results from Quarry.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
+    type Output = Vec<Vec<Value>>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
         let url = format!(
-            "https://quarry.wmcloud.org/query/{id}/result/latest/0/json",
+            "{base}/query/{id}/result/latest/0/json",
+            base = ti.endpoint(Endpoint::Quarry),
             id = self.id
         );
-        let client = crate::ToolsInterface::blocking_client()?;
-        let json: Value = client.get(&url).send()?.json()?;
-        self.set_from_json(json)
+        Ok(ToolRequest::get(url))
     }
 
-    #[cfg(feature = "tokio")]
-    /// Download the latest results from Quarry.
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = format!(
-            "https://quarry.wmcloud.org/query/{id}/result/latest/0/json",
-            id = self.id
-        );
-        let client = crate::ToolsInterface::tokio_client()?;
-        let json: Value = client.get(&url).send().await?.json().await?;
-        self.set_from_json(json)
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.rows.clone())
     }
+}
 
+impl Quarry {
     fn set_from_json(&mut self, json: Value) -> Result<(), ToolsError> {
-        self.columns = json
+        self.columns = Self::columns_from_json(&json)?;
+        self.rows = Self::rows_from_json(&json)?;
+        Ok(())
+    }
+
+    fn columns_from_json(json: &Value) -> Result<Vec<String>, ToolsError> {
+        Ok(json
             .get("headers")
-            .ok_or_else(|| ToolsError::Json("No headers in Quarry JSON".to_string()))?
+            .ok_or_else(|| ToolsError::parse("Quarry", "headers", "missing"))?
             .as_array()
-            .ok_or_else(|| {
-                ToolsError::Json("['headers'] is not an array in Quarry JSON".to_string())
-            })?
+            .ok_or_else(|| ToolsError::parse("Quarry", "headers", "not an array"))?
             .iter()
             .map(|s| s.as_str().unwrap_or("").to_string())
-            .collect();
+            .collect())
+    }
 
-        self.rows = json
+    fn rows_from_json(json: &Value) -> Result<Vec<Vec<Value>>, ToolsError> {
+        Ok(json
             .get("rows")
-            .ok_or_else(|| ToolsError::Json("No rows in Quarry JSON".to_string()))?
+            .ok_or_else(|| ToolsError::parse("Quarry", "rows", "missing"))?
             .as_array()
-            .ok_or_else(|| ToolsError::Json("Rows is not an array in Quarry JSON".to_string()))?
+            .ok_or_else(|| ToolsError::parse("Quarry", "rows", "not an array"))?
             .iter()
             .filter_map(|row| row.as_array())
             .map(|row| row.to_vec())
-            .collect();
+            .collect())
+    }
+}
 
-        Ok(())
+/// Quarry returns all rows of a result in one response, so there is only a single page.
+impl Paginated for Quarry {
+    type Item = Vec<Value>;
+    type Cursor = ();
+
+    fn page_request(
+        &self,
+        ti: &ToolsInterface,
+        _cursor: Option<&()>,
+    ) -> Result<ToolRequest, ToolsError> {
+        self.request(ti)
+    }
+
+    fn parse_page(
+        &self,
+        response: &ToolResponse,
+        _cursor: Option<&()>,
+    ) -> Result<ResultPage<Vec<Value>, ()>, ToolsError> {
+        Ok(ResultPage::last(Self::rows_from_json(&response.json()?)?))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::replay::Replay;
 
     #[cfg(feature = "blocking")]
     #[test]
     fn test_quarry_get_blocking() {
+        let replay = Replay::start_blocking("quarry_82868");
         let mut quarry = Quarry::new(82868); // dewiki root categories
-        quarry.run_blocking().unwrap();
+        quarry.run_blocking_with(&replay).unwrap();
         let column_number = quarry.colnum("page_title").unwrap();
         assert_eq!(column_number, 2);
-        assert!(quarry
-            .rows()
-            .iter()
-            .any(|row| row[column_number].as_str() == Some("!Hauptkategorie")));
+        assert!(
+            quarry
+                .rows()
+                .iter()
+                .any(|row| row[column_number].as_str() == Some("!Hauptkategorie"))
+        );
     }
 
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_quarry_get_async() {
+        let replay = Replay::start("quarry_82868").await;
         let mut quarry = Quarry::new(82868); // dewiki root categories
-        quarry.run().await.unwrap();
+        quarry.run_with(&replay).await.unwrap();
         let column_number = quarry.colnum("page_title").unwrap();
         assert_eq!(column_number, 2);
-        assert!(quarry
-            .rows()
-            .iter()
-            .any(|row| row[column_number].as_str() == Some("!Hauptkategorie")));
+        assert!(
+            quarry
+                .rows()
+                .iter()
+                .any(|row| row[column_number].as_str() == Some("!Hauptkategorie"))
+        );
     }
 }
@@ -0,0 +1,286 @@
+/// # Query spec
+/// `QuerySpec` is a saved query for any tool that returns a list of pages.
+/// It can be loaded from a JSON or TOML file, and run again to produce a `PageList`.
+/// The `tool` key selects the tool; all other keys are the tool configuration, with the same
+/// names as in the serialized tool. Sites can be given as a wiki name, e.g. `"dewiki"`.
+/// The resulting `PageList` records the tool, the parameters, and the time of the run as its `Provenance`.
+///
+/// ## Example
+/// ```toml
+/// tool = "petscan"
+/// psid = 28348714
+/// parameters = [["depth", "3"]]
+/// ```
+///
+/// ```ignore
+/// let spec = QuerySpec::from_file("weekly.toml")?;
+/// let page_list = spec.run().await?;
+/// ```
+use crate::{
+    AListBuildingTool, Completer, Duplicity, MissingTopics, PageList, PageListSource, PagePile,
+    PetScan, Tool, ToolsError, ToolsInterface, grep::Grep, list_building::ListBuilding,
+    search::WikiSearch, wiki_nearby::WikiNearby, xtools_pages::XtoolsPages,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Where a `PageList` came from: the tool, its parameters, and when it was run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub tool: String,
+    pub parameters: Value,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "tool")]
+pub enum QuerySpec {
+    #[serde(rename = "alistbuildingtool")]
+    AListBuildingTool(AListBuildingTool),
+    #[serde(rename = "completer")]
+    Completer(Completer),
+    #[serde(rename = "duplicity")]
+    Duplicity(Duplicity),
+    #[serde(rename = "grep")]
+    Grep(Grep),
+    #[serde(rename = "listbuilding")]
+    ListBuilding(ListBuilding),
+    #[serde(rename = "missing_topics")]
+    MissingTopics(MissingTopics),
+    #[serde(rename = "pagepile")]
+    PagePile(PagePile),
+    #[serde(rename = "petscan")]
+    PetScan(PetScan),
+    #[serde(rename = "search")]
+    WikiSearch(WikiSearch),
+    #[serde(rename = "wikinearby")]
+    WikiNearby(WikiNearby),
+    #[serde(rename = "xtools_pages")]
+    XtoolsPages(XtoolsPages),
+}
+
+impl QuerySpec {
+    /// Reads a query spec from a JSON string.
+    pub fn from_json(json: &str) -> Result<Self, ToolsError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Reads a query spec from a TOML string.
+    pub fn from_toml(toml: &str) -> Result<Self, ToolsError> {
+        toml::from_str(toml).map_err(|e| ToolsError::parse("QuerySpec", "toml", e.message()))
+    }
+
+    /// Reads a query spec from a file. Files ending in `.toml` are read as TOML, all others as JSON.
+    pub fn from_file(filename: &str) -> Result<Self, ToolsError> {
+        let text = std::fs::read_to_string(filename)?;
+        match filename.ends_with(".toml") {
+            true => Self::from_toml(&text),
+            false => Self::from_json(&text),
+        }
+    }
+
+    /// Returns the name of the tool, as used in the `tool` key.
+    pub fn tool_name(&self) -> &'static str {
+        match self {
+            QuerySpec::AListBuildingTool(_) => "alistbuildingtool",
+            QuerySpec::Completer(_) => "completer",
+            QuerySpec::Duplicity(_) => "duplicity",
+            QuerySpec::Grep(_) => "grep",
+            QuerySpec::ListBuilding(_) => "listbuilding",
+            QuerySpec::MissingTopics(_) => "missing_topics",
+            QuerySpec::PagePile(_) => "pagepile",
+            QuerySpec::PetScan(_) => "petscan",
+            QuerySpec::WikiSearch(_) => "search",
+            QuerySpec::WikiNearby(_) => "wikinearby",
+            QuerySpec::XtoolsPages(_) => "xtools_pages",
+        }
+    }
+
+    /// Returns the keys of the serialized tool that hold the results of a run, not its configuration.
+    fn result_keys(&self) -> &'static [&'static str] {
+        match self {
+            QuerySpec::AListBuildingTool(_) => &["results"],
+            QuerySpec::Completer(_) => &["id", "results"],
+            QuerySpec::Duplicity(_) => &["results"],
+            QuerySpec::Grep(_) => &["results"],
+            QuerySpec::ListBuilding(_) => &["results"],
+            QuerySpec::MissingTopics(_) => &["url_used", "results"],
+            QuerySpec::PagePile(_) => &["prefixed_titles", "language", "project", "wiki"],
+            QuerySpec::PetScan(_) => &["pages", "namespaces", "query", "wiki", "status"],
+            QuerySpec::WikiSearch(_) => &["results"],
+            QuerySpec::WikiNearby(_) => &["results", "lat", "lon"],
+            QuerySpec::XtoolsPages(_) => &["results"],
+        }
+    }
+
+    /// Returns the provenance of a run of this query, starting now.
+    /// The parameters are the inputs of the query; results of an earlier run are not included.
+    pub fn provenance(&self) -> Result<Provenance, ToolsError> {
+        let mut parameters = serde_json::to_value(self)?;
+        if let Some(parameters) = parameters.as_object_mut() {
+            parameters.remove("tool");
+            for key in self.result_keys() {
+                parameters.remove(*key);
+            }
+        }
+        Ok(Provenance {
+            tool: self.tool_name().to_string(),
+            parameters,
+            timestamp: Utc::now(),
+        })
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Runs the query, using the process-wide `ToolsInterface`.
+    pub async fn run(&self) -> Result<PageList, ToolsError> {
+        self.run_with(&ToolsInterface::global()).await
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Runs the query with the given `ToolsInterface`, and returns the results with provenance.
+    pub async fn run_with(&self, ti: &ToolsInterface) -> Result<PageList, ToolsError> {
+        let provenance = self.provenance()?;
+        let page_list = match self {
+            QuerySpec::AListBuildingTool(tool) => run_tool(tool, ti).await,
+            QuerySpec::Completer(tool) => run_tool(tool, ti).await,
+            QuerySpec::Duplicity(tool) => run_tool(tool, ti).await,
+            QuerySpec::Grep(tool) => run_tool(tool, ti).await,
+            QuerySpec::ListBuilding(tool) => run_tool(tool, ti).await,
+            QuerySpec::MissingTopics(tool) => run_tool(tool, ti).await,
+            QuerySpec::PagePile(tool) => run_tool(tool, ti).await,
+            QuerySpec::PetScan(tool) => run_tool(tool, ti).await,
+            QuerySpec::WikiSearch(tool) => run_tool(tool, ti).await,
+            QuerySpec::WikiNearby(tool) => run_tool(tool, ti).await,
+            QuerySpec::XtoolsPages(tool) => run_tool(tool, ti).await,
+        }?;
+        Ok(page_list.with_provenance(provenance))
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `run`, in a blocking manner.
+    pub fn run_blocking(&self) -> Result<PageList, ToolsError> {
+        self.run_blocking_with(&ToolsInterface::global())
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `run_with`, in a blocking manner.
+    pub fn run_blocking_with(&self, ti: &ToolsInterface) -> Result<PageList, ToolsError> {
+        let provenance = self.provenance()?;
+        let page_list = match self {
+            QuerySpec::AListBuildingTool(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::Completer(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::Duplicity(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::Grep(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::ListBuilding(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::MissingTopics(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::PagePile(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::PetScan(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::WikiSearch(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::WikiNearby(tool) => run_tool_blocking(tool, ti),
+            QuerySpec::XtoolsPages(tool) => run_tool_blocking(tool, ti),
+        }?;
+        Ok(page_list.with_provenance(provenance))
+    }
+}
+
+/// Runs a copy of the tool, so the spec itself stays unchanged and can be run again.
+#[cfg(feature = "tokio")]
+async fn run_tool<T: Tool + PageListSource + Clone>(
+    tool: &T,
+    ti: &ToolsInterface,
+) -> Result<PageList, ToolsError> {
+    let mut tool = tool.clone();
+    tool.run_with(ti).await?;
+    tool.page_list().await
+}
+
+#[cfg(feature = "blocking")]
+fn run_tool_blocking<T: Tool + PageListSource + Clone>(
+    tool: &T,
+    ti: &ToolsInterface,
+) -> Result<PageList, ToolsError> {
+    let mut tool = tool.clone();
+    tool.run_blocking_with(ti)?;
+    tool.page_list_blocking()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Site;
+
+    #[test]
+    fn test_from_json() {
+        let spec = QuerySpec::from_json(
+            r#"{"tool":"missing_topics","site":"dewiki","article":"Biologie","no_template_links":true}"#,
+        )
+        .unwrap();
+        let expected = MissingTopics::new(Site::from_wiki("dewiki").unwrap())
+            .with_article("Biologie")
+            .no_template_links(true);
+        assert_eq!(spec, QuerySpec::MissingTopics(expected));
+        assert_eq!(spec.tool_name(), "missing_topics");
+    }
+
+    #[test]
+    fn test_from_toml() {
+        let spec = QuerySpec::from_toml(
+            "tool = \"petscan\"\npsid = 25951472\nparameters = [[\"depth\", \"3\"]]\n",
+        )
+        .unwrap();
+        let mut expected = PetScan::new(25951472);
+        expected
+            .parameters_mut()
+            .push(("depth".to_string(), "3".to_string()));
+        assert_eq!(spec, QuerySpec::PetScan(expected));
+
+        let spec = QuerySpec::from_toml("tool = \"completer\"\nlang_from = \"de\"\nlang_to = \"en\"\n\n[[filters]]\ntype = \"template\"\ntemplate = \"Infobox\"\n").unwrap();
+        assert_eq!(spec.tool_name(), "completer");
+        assert!(QuerySpec::from_toml("tool = \"nonexistent\"").is_err());
+    }
+
+    #[test]
+    fn test_provenance() {
+        let spec = QuerySpec::PagePile(PagePile::new(12345));
+        let provenance = spec.provenance().unwrap();
+        assert_eq!(provenance.tool, "pagepile");
+        assert_eq!(provenance.parameters["id"], 12345);
+        assert!(provenance.parameters.get("tool").is_none());
+        let keys: Vec<_> = provenance.parameters.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["id"]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_run_with() {
+        let replay = crate::replay::Replay::start("petscan_25951472").await;
+        let spec = QuerySpec::from_json(r#"{"tool":"petscan","psid":25951472}"#).unwrap();
+        let page_list = spec.run_with(&replay).await.unwrap();
+        assert_eq!(page_list.pages().len(), 1);
+        assert_eq!(page_list.pages()[0].title().pretty(), "Magnus Manske");
+        let provenance = page_list.provenance().unwrap();
+        assert_eq!(provenance.tool, "petscan");
+        assert_eq!(provenance.parameters["psid"], 25951472);
+
+        // The spec itself is unchanged, and can be run again
+        assert_eq!(spec.run_with(&replay).await.unwrap().pages().len(), 1);
+
+        // Results of a tool that has already run are not part of the parameters
+        let mut tool = PetScan::new(25951472);
+        tool.run_with(&replay).await.unwrap();
+        let spec = QuerySpec::PetScan(tool);
+        let provenance = spec.provenance().unwrap();
+        for key in ["pages", "namespaces", "query", "wiki", "status"] {
+            assert!(provenance.parameters.get(key).is_none(), "{key}");
+        }
+        assert_eq!(provenance.parameters["psid"], 25951472);
+
+        // The tool itself still carries the results
+        let QuerySpec::PetScan(tool) = spec else {
+            unreachable!()
+        };
+        let json = serde_json::to_string(&tool).unwrap();
+        assert_eq!(serde_json::from_str::<PetScan>(&json).unwrap(), tool);
+    }
+}
@@ -14,13 +14,15 @@
 /// qs.run().await.unwrap();
 /// let batch_id = qs.batch_id().unwrap();
 /// ```
-use crate::{Tool, ToolsError};
+use crate::{Endpoint, Tool, ToolRequest, ToolResponse, ToolsError, ToolsInterface};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct QuickStatements {
-    petscan_uri: String, // For testing
+    #[serde(skip_serializing)]
     token: String,
     user_name: String,
     compress: bool,
@@ -38,7 +40,6 @@ impl QuickStatements {
     /// For this to work, you need to have run a batch (server side) before manually, so your OAuth details can be filled in once.
     pub fn new<S1: Into<String>, S2: Into<String>>(user_name: S1, token: S2) -> Self {
         Self {
-            petscan_uri: "https://quickstatements.toolforge.org/api.php".to_string(),
             token: token.into(),
             user_name: user_name.into(),
             compress: true,
@@ -72,7 +73,22 @@ impl QuickStatements {
 
 #[async_trait]
 impl Tool for QuickStatements {
-    fn generate_paramters(&self) -> Result<Vec<(String, String)>, ToolsError> {
+    type Output = Option<u64>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        let url = format!("{}/api.php", ti.endpoint(Endpoint::QuickStatements));
+        let params = self.generate_paramters()?;
+        Ok(ToolRequest::post(url).form(params))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.batch_id)
+    }
+}
+
+impl QuickStatements {
+    pub fn generate_paramters(&self) -> Result<Vec<(String, String)>, ToolsError> {
         let params = [
             ("action", "import"),
             ("submit", "1"),
@@ -91,52 +107,22 @@ impl Tool for QuickStatements {
         Ok(ret)
     }
 
-    #[cfg(feature = "blocking")]
-    /// Starts the server-side batch and consumes the QuickStatements object.
-    /// Returns the batch ID if successful.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        let url = &self.petscan_uri;
-        let params = self.generate_paramters()?;
-        let client = crate::ToolsInterface::blocking_client()?;
-        let j: Value = client.post(url).form(&params).send()?.json()?;
+    fn set_from_json(&mut self, j: Value) -> Result<(), ToolsError> {
         let status = j["status"]
             .as_str()
-            .ok_or(ToolsError::Json("['status'] is not a string".into()))?;
+            .ok_or_else(|| ToolsError::parse("QuickStatements", "status", "not a string"))?;
         if status != "OK" {
-            return Err(ToolsError::Json(format!(
-                "QuickStatements status is not OK: {:?}",
-                status
-            )));
-        }
-        self.batch_id = j["batch_id"].as_u64();
-        Ok(())
-    }
-
-    #[cfg(feature = "tokio")]
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = &self.petscan_uri;
-        let params = self.generate_paramters()?;
-        let client = crate::ToolsInterface::tokio_client()?;
-        let response = client.post(url).form(&params).send().await?;
-        let j: Value = response.json().await?;
-
-        let status = j["status"]
-            .as_str()
-            .ok_or(ToolsError::Json("['status'] is not a string".into()))?;
-        if status != "OK" {
-            return Err(ToolsError::Json(format!(
-                "QuickStatements status is not OK: {:?}",
-                status
-            )));
+            return Err(ToolsError::tool_status("QuickStatements", status));
         }
         self.batch_id = j["batch_id"].as_u64();
         Ok(())
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "tokio"))]
 mod tests {
     use super::*;
+    use crate::replay::Replay;
     use serde_json::json;
     use wiremock::matchers::{body_string_contains, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
@@ -171,9 +157,21 @@ mod tests {
             .mount(&mock_server)
             .await;
         let mut qs = QuickStatements::new("Magnus_Manske", token).batch_name("foobar");
-        qs.petscan_uri = format!("{}{mock_path}", mock_server.uri());
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::QuickStatements, mock_server.uri())
+            .build()
+            .unwrap();
         qs.add_command("Q4115189\tP31\tQ1");
-        qs.run().await.unwrap();
+        qs.run_with(&ti).await.unwrap();
         assert_eq!(qs.batch_id(), Some(12345));
     }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_quickstatements_replay() {
+        let replay = Replay::start("quickstatements_import").await;
+        let mut qs = QuickStatements::new("Magnus_Manske", "FAKE_TOKEN").batch_name("foobar");
+        qs.add_command("Q4115189\tP31\tQ1");
+        assert_eq!(qs.run_with(&replay).await.unwrap(), Some(12345));
+    }
 }
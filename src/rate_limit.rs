@@ -0,0 +1,255 @@
+/// # Rate limit
+/// `RateLimiter` throttles requests per host, with a maximum number of requests per second
+/// and a maximum number of requests in flight at the same time.
+/// It is part of a `ToolsInterface`, and applies to every request sent through it,
+/// as well as to the MediaWiki API helpers.
+/// Hosts without an explicit `RateLimit` use the default limit, which is unlimited unless set otherwise.
+/// Async and blocking requests share the requests-per-second budget, but count their requests in flight separately.
+///
+/// ## Example
+/// ```ignore
+/// let ti = ToolsInterface::builder()
+///     .rate_limit("petscan.wmflabs.org", RateLimit::default().with_max_in_flight(2))
+///     .default_rate_limit(RateLimit::default().with_requests_per_second(10.0))
+///     .build()
+///     .unwrap();
+/// ```
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RateLimit {
+    requests_per_second: Option<f64>,
+    max_in_flight: Option<usize>,
+}
+
+impl RateLimit {
+    /// Limits the number of requests started per second. Non-positive values remove the limit.
+    pub fn with_requests_per_second(mut self, requests_per_second: f64) -> Self {
+        self.requests_per_second = (requests_per_second > 0.0).then_some(requests_per_second);
+        self
+    }
+
+    /// Limits the number of concurrent requests. Zero removes the limit.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = (max_in_flight > 0).then_some(max_in_flight);
+        self
+    }
+
+    pub fn requests_per_second(&self) -> Option<f64> {
+        self.requests_per_second
+    }
+
+    pub fn max_in_flight(&self) -> Option<usize> {
+        self.max_in_flight
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        self.requests_per_second
+            .map(|rps| Duration::from_secs_f64(1.0 / rps))
+    }
+}
+
+#[derive(Debug)]
+struct HostState {
+    limit: RateLimit,
+    next_slot: Mutex<Option<Instant>>,
+    #[cfg(feature = "tokio")]
+    semaphore: Option<Arc<tokio::sync::Semaphore>>,
+    in_flight_blocking: Mutex<usize>,
+    in_flight_released: Condvar,
+}
+
+impl HostState {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            next_slot: Mutex::new(None),
+            #[cfg(feature = "tokio")]
+            semaphore: limit
+                .max_in_flight
+                .map(|n| Arc::new(tokio::sync::Semaphore::new(n))),
+            in_flight_blocking: Mutex::new(0),
+            in_flight_released: Condvar::new(),
+        }
+    }
+
+    /// Reserves the next start slot, and returns how long to wait for it.
+    fn reserve_slot(&self) -> Duration {
+        let interval = match self.limit.interval() {
+            Some(interval) => interval,
+            None => return Duration::ZERO,
+        };
+        let now = Instant::now();
+        let mut next_slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+        let slot = next_slot.map_or(now, |next| next.max(now));
+        *next_slot = Some(slot + interval);
+        slot - now
+    }
+}
+
+/// Holds a place among the requests in flight for a host. The place is released on drop.
+#[derive(Debug)]
+pub struct RateLimitPermit {
+    #[cfg(feature = "tokio")]
+    _permit: Option<tokio::sync::OwnedSemaphorePermit>,
+    blocking_host: Option<Arc<HostState>>,
+}
+
+impl Drop for RateLimitPermit {
+    fn drop(&mut self) {
+        if let Some(host) = &self.blocking_host {
+            let mut in_flight = host
+                .in_flight_blocking
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            *in_flight = in_flight.saturating_sub(1);
+            host.in_flight_released.notify_one();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    default_limit: RateLimit,
+    host_limits: HashMap<String, RateLimit>,
+    hosts: Mutex<HashMap<String, Arc<HostState>>>,
+}
+
+impl RateLimiter {
+    /// Creates a new limiter. `host_limits` override `default_limit` for specific hosts.
+    pub fn new(default_limit: RateLimit, host_limits: HashMap<String, RateLimit>) -> Self {
+        let host_limits = host_limits
+            .into_iter()
+            .map(|(host, limit)| (host.to_ascii_lowercase(), limit))
+            .collect();
+        Self {
+            default_limit,
+            host_limits,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the limit that applies to a host.
+    pub fn limit_for(&self, host: &str) -> RateLimit {
+        self.host_limits
+            .get(&host.to_ascii_lowercase())
+            .copied()
+            .unwrap_or(self.default_limit)
+    }
+
+    fn host_state(&self, host: &str) -> Arc<HostState> {
+        let host = host.to_ascii_lowercase();
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        hosts
+            .entry(host.clone())
+            .or_insert_with(|| Arc::new(HostState::new(self.limit_for(&host))))
+            .clone()
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Waits until a request to `host` may start.
+    pub async fn acquire(&self, host: &str) -> RateLimitPermit {
+        let state = self.host_state(host);
+        let permit = match &state.semaphore {
+            // The semaphore is never closed, so this can not fail
+            Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+        let wait = state.reserve_slot();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        RateLimitPermit {
+            _permit: permit,
+            blocking_host: None,
+        }
+    }
+
+    /// Blocks until a request to `host` may start.
+    pub fn acquire_blocking(&self, host: &str) -> RateLimitPermit {
+        let state = self.host_state(host);
+        let blocking_host = match state.limit.max_in_flight {
+            Some(max_in_flight) => {
+                let mut in_flight = state
+                    .in_flight_blocking
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                while *in_flight >= max_in_flight {
+                    in_flight = state
+                        .in_flight_released
+                        .wait(in_flight)
+                        .unwrap_or_else(|e| e.into_inner());
+                }
+                *in_flight += 1;
+                Some(state.clone())
+            }
+            None => None,
+        };
+        let wait = state.reserve_slot();
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+        RateLimitPermit {
+            #[cfg(feature = "tokio")]
+            _permit: None,
+            blocking_host,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_for() {
+        let limiter = RateLimiter::new(
+            RateLimit::default().with_max_in_flight(3),
+            [(
+                "PetScan.wmflabs.org".to_string(),
+                RateLimit::default().with_requests_per_second(2.0),
+            )]
+            .into(),
+        );
+        assert_eq!(limiter.limit_for("example.org").max_in_flight(), Some(3));
+        assert_eq!(
+            limiter
+                .limit_for("petscan.wmflabs.org")
+                .requests_per_second(),
+            Some(2.0)
+        );
+        assert_eq!(
+            limiter.limit_for("petscan.wmflabs.org").max_in_flight(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_requests_per_second_blocking() {
+        let limit = RateLimit::default().with_requests_per_second(20.0);
+        let limiter = RateLimiter::new(limit, HashMap::new());
+        let start = Instant::now();
+        for _ in 0..5 {
+            let _permit = limiter.acquire_blocking("example.org");
+        }
+        // First request is immediate, then four intervals of 50ms
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_max_in_flight_async() {
+        let limit = RateLimit::default().with_max_in_flight(1);
+        let limiter = RateLimiter::new(limit, HashMap::new());
+        let permit = limiter.acquire("example.org").await;
+        let second =
+            tokio::time::timeout(Duration::from_millis(50), limiter.acquire("example.org")).await;
+        assert!(second.is_err());
+        drop(permit);
+        let third =
+            tokio::time::timeout(Duration::from_millis(50), limiter.acquire("example.org")).await;
+        assert!(third.is_ok());
+    }
+}
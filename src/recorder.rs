@@ -0,0 +1,218 @@
+/// # Recorder
+/// A `Recorder` captures the HTTP exchanges (request and response) of a `ToolsInterface`,
+/// so they can be saved as a `Fixture` file and replayed later, e.g. by a mock server in tests.
+/// Fixtures are JSON files; response bodies that are valid JSON are stored as JSON, to keep them readable.
+/// Secret request parameters, like the QuickStatements token, are redacted before recording.
+///
+/// This crate's own tests replay fixtures from `test_data/fixtures/`.
+/// Running the tests with the environment variable `TOOLS_INTERFACE_RECORD=1` queries the live tools instead,
+/// and overwrites the fixtures with the new responses.
+///
+/// ## Example
+/// ```ignore
+/// let recorder = Arc::new(Recorder::new());
+/// let ti = ToolsInterface::builder().recorder(recorder.clone()).build().unwrap();
+/// let mut ps = PetScan::new(25951472);
+/// ps.run_with(&ti).await.unwrap();
+/// recorder.save("test_data/fixtures/petscan.json").unwrap();
+/// ```
+use crate::request::RequestBody;
+use crate::{ToolRequest, ToolResponse, ToolsError};
+use reqwest::Method;
+use serde_json::{Value, json};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::sync::Mutex;
+
+/// A request, and the response it got.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exchange {
+    pub request: ToolRequest,
+    pub response: ToolResponse,
+}
+
+impl Exchange {
+    /// Reads an exchange from fixture JSON.
+    /// The query parameters of the request are part of its URL.
+    pub fn from_json(j: &Value) -> Result<Self, ToolsError> {
+        let method = j["request"]["method"].as_str().unwrap_or("GET");
+        let method = Method::from_bytes(method.as_bytes())
+            .map_err(|_| ToolsError::parse("Fixture", "request.method", method))?;
+        let url = j["request"]["url"]
+            .as_str()
+            .ok_or_else(|| ToolsError::parse("Fixture", "request.url", "missing"))?;
+        let mut request = ToolRequest::new(method, url);
+        if let Some(body) = j["request"].get("json") {
+            request = request.json(body.to_owned());
+        } else if let Some(form) = j["request"]["form"].as_array() {
+            let form = form
+                .iter()
+                .filter_map(|pair| Some((pair[0].as_str()?.into(), pair[1].as_str()?.into())))
+                .collect();
+            request = request.form(form);
+        }
+
+        let response = &j["response"];
+        let status = response["status"]
+            .as_u64()
+            .ok_or_else(|| ToolsError::parse("Fixture", "response.status", "missing"))?;
+        let headers = response["headers"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(k, v)| Some((k.to_owned(), v.as_str()?.to_owned())))
+            .collect();
+        let body = match (response.get("json"), response["text"].as_str()) {
+            (Some(body), _) => body.to_string().into_bytes(),
+            (None, Some(text)) => text.as_bytes().to_vec(),
+            (None, None) => vec![],
+        };
+        let response = ToolResponse::new(status as u16, headers, body);
+        Ok(Self { request, response })
+    }
+
+    /// Returns the exchange as fixture JSON, as read by `from_json`.
+    pub fn to_json(&self) -> Value {
+        let mut request = json!({
+            "method": self.request.method().as_str(),
+            "url": self.request.full_url(),
+        });
+        match self.request.body() {
+            RequestBody::None => {}
+            RequestBody::Json(body) => request["json"] = body.to_owned(),
+            RequestBody::Form(form) => request["form"] = json!(form),
+        }
+
+        let headers: serde_json::Map<String, Value> = self
+            .response
+            .headers()
+            .iter()
+            .map(|(k, v)| (k.to_owned(), json!(v)))
+            .collect();
+        let mut response = json!({
+            "status": self.response.status(),
+            "headers": headers,
+        });
+        match self.response.json() {
+            Ok(body) => response["json"] = body,
+            Err(_) => response["text"] = json!(self.response.text()),
+        }
+        json!({"request": request, "response": response})
+    }
+}
+
+/// A sequence of recorded exchanges.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fixture {
+    pub exchanges: Vec<Exchange>,
+}
+
+impl Fixture {
+    /// Reads a fixture from JSON, as written by `to_json`.
+    pub fn from_json(j: &Value) -> Result<Self, ToolsError> {
+        let exchanges = j["exchanges"]
+            .as_array()
+            .ok_or_else(|| ToolsError::parse("Fixture", "exchanges", "not an array"))?
+            .iter()
+            .map(Exchange::from_json)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { exchanges })
+    }
+
+    /// Reads a fixture from a JSON file.
+    pub fn from_file(filename: &str) -> Result<Self, ToolsError> {
+        let file = File::open(filename)?;
+        let reader = BufReader::new(file);
+        let j: Value = serde_json::from_reader(reader)?;
+        Self::from_json(&j)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let exchanges: Vec<Value> = self.exchanges.iter().map(|e| e.to_json()).collect();
+        json!({"exchanges": exchanges})
+    }
+
+    /// Writes the fixture to a JSON file.
+    pub fn save(&self, filename: &str) -> Result<(), ToolsError> {
+        let file = File::create(filename)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.to_json())?;
+        Ok(())
+    }
+}
+
+/// Collects the exchanges of a `ToolsInterface`, see `ToolsInterfaceBuilder::recorder`.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an exchange, with secret parameters redacted.
+    /// Called by the `ToolsInterface` for every response, including retries.
+    pub fn record(&self, request: &ToolRequest, response: &ToolResponse) {
+        self.exchanges
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Exchange {
+                request: request.redacted(),
+                response: response.to_owned(),
+            });
+    }
+
+    /// Returns the exchanges recorded so far.
+    pub fn fixture(&self) -> Fixture {
+        let exchanges = self
+            .exchanges
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        Fixture { exchanges }
+    }
+
+    /// Writes the exchanges recorded so far to a fixture file.
+    pub fn save(&self, filename: &str) -> Result<(), ToolsError> {
+        self.fixture().save(filename)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_roundtrip() {
+        let recorder = Recorder::new();
+        let request = ToolRequest::post("https://example.org/api.php?action=foo")
+            .query(&[("bar".to_string(), "baz qux".to_string())])
+            .form(vec![("token".to_string(), "123".to_string())]);
+        recorder.record(
+            &request,
+            &ToolResponse::new(200, vec![], br#"{"status":"OK"}"#.to_vec()),
+        );
+        recorder.record(
+            &ToolRequest::get("https://example.org/"),
+            &ToolResponse::new(404, vec![], b"Not found".to_vec()),
+        );
+        let j = recorder.fixture().to_json();
+        assert_eq!(j["exchanges"][0]["request"]["form"][0][1], "REDACTED");
+        assert_eq!(j["exchanges"][0]["response"]["json"]["status"], "OK");
+        assert_eq!(j["exchanges"][1]["response"]["text"], "Not found");
+
+        let fixture = Fixture::from_json(&j).unwrap();
+        assert_eq!(fixture.exchanges.len(), 2);
+        assert_eq!(
+            fixture.exchanges[0].request.full_url(),
+            "https://example.org/api.php?action=foo&bar=baz+qux"
+        );
+        assert_eq!(
+            fixture.exchanges[0].request.body(),
+            request.redacted().body()
+        );
+        assert_eq!(fixture.exchanges[1].response.status(), 404);
+        assert_eq!(fixture.to_json(), j);
+    }
+}
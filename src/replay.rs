@@ -0,0 +1,124 @@
+/// # Replay
+/// Test helper that serves a recorded `Fixture` from a `wiremock` server.
+/// `Replay::start("name")` reads `test_data/fixtures/name.json`, and returns a `ToolsInterface`
+/// that sends the requests for every host in the fixture to the mock server.
+/// With the environment variable `TOOLS_INTERFACE_RECORD` set, the live tools are queried instead,
+/// and the fixture is (over)written when the `Replay` is dropped.
+///
+/// ## Example
+/// ```ignore
+/// let replay = Replay::start("petscan_25951472").await;
+/// let mut ps = PetScan::new(25951472);
+/// ps.run_with(&replay).await.unwrap();
+/// ```
+use crate::{Fixture, Recorder, RetryPolicy, ToolsInterface};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const RECORD_ENV: &str = "TOOLS_INTERFACE_RECORD";
+const SKIPPED_HEADERS: &[&str] = &[
+    "connection",
+    "content-encoding",
+    "content-length",
+    "transfer-encoding",
+];
+
+pub(crate) struct Replay {
+    ti: ToolsInterface,
+    _server: Option<MockServer>,
+    recording: Option<(Arc<Recorder>, String)>,
+}
+
+impl Replay {
+    pub(crate) async fn start(name: &str) -> Self {
+        let filename = format!(
+            "{}/test_data/fixtures/{name}.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        if std::env::var_os(RECORD_ENV).is_some() {
+            let recorder = Arc::new(Recorder::new());
+            let ti = ToolsInterface::builder()
+                .recorder(recorder.clone())
+                .build()
+                .expect("ToolsInterface should build");
+            return Self {
+                ti,
+                _server: None,
+                recording: Some((recorder, filename)),
+            };
+        }
+
+        let fixture = Fixture::from_file(&filename).unwrap_or_else(|e| {
+            panic!("Can not read fixture {filename} ({e}), record it with {RECORD_ENV}=1")
+        });
+        let server = MockServer::start().await;
+        let mut builder = ToolsInterface::builder().retry_policy(RetryPolicy::none());
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for exchange in &fixture.exchanges {
+            *seen.entry(exchange.request.full_url()).or_default() += 1;
+        }
+        for exchange in &fixture.exchanges {
+            let full_url = exchange.request.full_url();
+            let url = reqwest::Url::parse(&full_url).expect("Fixture URL should be valid");
+            let host = url.host_str().expect("Fixture URL should have a host");
+            builder = builder.host_override(host, format!("{}/{host}", server.uri()));
+
+            let response = &exchange.response;
+            let mut template =
+                ResponseTemplate::new(response.status()).set_body_bytes(response.body());
+            for (name, value) in response.headers() {
+                // The body may have been reformatted, and is served uncompressed
+                if SKIPPED_HEADERS.contains(&name.as_str()) {
+                    continue;
+                }
+                template = template.insert_header(name.as_str(), value.as_str());
+            }
+            let mut mock = Mock::given(method(exchange.request.method().as_str()))
+                .and(path(format!("/{host}{}", url.path())));
+            for (key, value) in url.query_pairs() {
+                mock = mock.and(query_param(key, value));
+            }
+            // Repeated requests get the recorded responses in order; the last one is kept
+            let remaining = seen.get_mut(&full_url).expect("URL was counted");
+            *remaining -= 1;
+            let mock = match remaining {
+                0 => mock.respond_with(template),
+                _ => mock.respond_with(template).up_to_n_times(1),
+            };
+            mock.mount(&server).await;
+        }
+        let ti = builder.build().expect("ToolsInterface should build");
+        Self {
+            ti,
+            _server: Some(server),
+            recording: None,
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `start`, for tests without an async runtime.
+    pub(crate) fn start_blocking(name: &str) -> Self {
+        futures::executor::block_on(Self::start(name))
+    }
+}
+
+impl Deref for Replay {
+    type Target = ToolsInterface;
+
+    fn deref(&self) -> &ToolsInterface {
+        &self.ti
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        if let Some((recorder, filename)) = &self.recording
+            && let Err(e) = recorder.save(filename)
+        {
+            eprintln!("Can not write fixture {filename}: {e}");
+        }
+    }
+}
@@ -0,0 +1,393 @@
+/// # Request
+/// `ToolRequest` describes an HTTP request to a tool, independent of the HTTP client used to send it.
+/// `ToolResponse` is the fully read response.
+/// Both are used by `ToolsInterface::execute` and `ToolsInterface::execute_blocking`,
+/// which send the request through a `Transport`, and add retries etc.
+///
+/// ## Example
+/// ```ignore
+/// let request = ToolRequest::get("https://example.org/api.php").query(&[("foo".into(), "bar".into())]);
+/// let response = ToolsInterface::global().execute(&request).await.unwrap();
+/// let j = response.json().unwrap();
+/// ```
+use crate::ToolsError;
+use crate::retry::RetryPolicy;
+use reqwest::Method;
+use serde_json::Value;
+
+/// Query and form parameters with secret values, e.g. the QuickStatements token.
+const SECRET_PARAMETERS: &[&str] = &["token", "password"];
+const REDACTED: &str = "REDACTED";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum RequestBody {
+    #[default]
+    None,
+    Json(Value),
+    Form(Vec<(String, String)>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolRequest {
+    method: Method,
+    url: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: RequestBody,
+}
+
+impl ToolRequest {
+    /// Creates a new request with the given method and URL.
+    pub fn new<S: Into<String>>(method: Method, url: S) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            query: vec![],
+            headers: vec![],
+            body: RequestBody::None,
+        }
+    }
+
+    /// Creates a new GET request.
+    pub fn get<S: Into<String>>(url: S) -> Self {
+        Self::new(Method::GET, url)
+    }
+
+    /// Creates a new POST request.
+    pub fn post<S: Into<String>>(url: S) -> Self {
+        Self::new(Method::POST, url)
+    }
+
+    /// Adds query parameters to the URL.
+    pub fn query(mut self, parameters: &[(String, String)]) -> Self {
+        self.query.extend_from_slice(parameters);
+        self
+    }
+
+    /// Adds a header, replacing any header with the same name (case-insensitive).
+    pub fn header<S1: Into<String>, S2: Into<String>>(mut self, name: S1, value: S2) -> Self {
+        let name: String = name.into();
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Sets a JSON body.
+    pub fn json(mut self, j: Value) -> Self {
+        self.body = RequestBody::Json(j);
+        self
+    }
+
+    /// Sets a form-encoded body.
+    pub fn form(mut self, parameters: Vec<(String, String)>) -> Self {
+        self.body = RequestBody::Form(parameters);
+        self
+    }
+
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the host part of the URL, if it can be parsed.
+    pub fn host(&self) -> Option<String> {
+        let url = reqwest::Url::parse(&self.url).ok()?;
+        url.host_str().map(|host| host.to_string())
+    }
+
+    /// Returns a key that identifies this request by method, URL, query parameters and body.
+    pub fn cache_key(&self) -> String {
+        let mut key = format!("{} {}", self.method, self.url);
+        if !self.query.is_empty() {
+            let query: Vec<String> = self.query.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            key += if self.url.contains('?') { "&" } else { "?" };
+            key += &query.join("&");
+        }
+        match &self.body {
+            RequestBody::None => {}
+            RequestBody::Json(j) => key += &format!("\n{j}"),
+            RequestBody::Form(parameters) => key += &format!("\n{parameters:?}"),
+        }
+        key
+    }
+
+    /// Returns the URL with the query parameters appended, as it is sent.
+    pub fn full_url(&self) -> String {
+        match reqwest::Url::parse(&self.url) {
+            Ok(mut url) => {
+                if !self.query.is_empty() {
+                    url.query_pairs_mut().extend_pairs(&self.query);
+                }
+                url.to_string()
+            }
+            Err(_) => self.url.to_owned(),
+        }
+    }
+
+    /// Returns a copy of this request with the values of secret parameters replaced,
+    /// for logging and recording.
+    pub fn redacted(&self) -> Self {
+        let redact = |parameters: &[(String, String)]| -> Vec<(String, String)> {
+            parameters
+                .iter()
+                .map(|(k, v)| match SECRET_PARAMETERS.contains(&k.as_str()) {
+                    true => (k.to_owned(), REDACTED.to_string()),
+                    false => (k.to_owned(), v.to_owned()),
+                })
+                .collect()
+        };
+        let mut url = self.url.to_owned();
+        if let Ok(mut parsed) = reqwest::Url::parse(&self.url)
+            && parsed
+                .query_pairs()
+                .any(|(k, _)| SECRET_PARAMETERS.contains(&k.as_ref()))
+        {
+            let pairs: Vec<(String, String)> = parsed.query_pairs().into_owned().collect();
+            parsed
+                .query_pairs_mut()
+                .clear()
+                .extend_pairs(redact(&pairs));
+            url = parsed.to_string();
+        }
+        let body = match &self.body {
+            RequestBody::Form(parameters) => RequestBody::Form(redact(parameters)),
+            body => body.to_owned(),
+        };
+        Self {
+            method: self.method.clone(),
+            url,
+            query: redact(&self.query),
+            headers: self.headers.clone(),
+            body,
+        }
+    }
+
+    /// Returns a copy of this request, sent to another URL.
+    pub(crate) fn with_url<S: Into<String>>(&self, url: S) -> Self {
+        Self {
+            url: url.into(),
+            ..self.clone()
+        }
+    }
+
+    pub fn query_parameters(&self) -> &[(String, String)] {
+        &self.query
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &RequestBody {
+        &self.body
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn to_reqwest(&self, client: &reqwest::Client) -> reqwest::RequestBuilder {
+        let mut builder = client
+            .request(self.method.clone(), &self.url)
+            .query(&self.query);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        match &self.body {
+            RequestBody::None => builder,
+            RequestBody::Json(j) => builder.json(j),
+            RequestBody::Form(parameters) => builder.form(parameters),
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn to_reqwest_blocking(
+        &self,
+        client: &reqwest::blocking::Client,
+    ) -> reqwest::blocking::RequestBuilder {
+        let mut builder = client
+            .request(self.method.clone(), &self.url)
+            .query(&self.query);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        match &self.body {
+            RequestBody::None => builder,
+            RequestBody::Json(j) => builder.json(j),
+            RequestBody::Form(parameters) => builder.form(parameters),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ToolResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    url: Option<String>,
+}
+
+impl ToolResponse {
+    /// Creates a new response. Header names are stored in lowercase.
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: Vec<u8>) -> Self {
+        let headers = headers
+            .into_iter()
+            .map(|(k, v)| (k.to_ascii_lowercase(), v))
+            .collect();
+        Self {
+            status,
+            headers,
+            body,
+            url: None,
+        }
+    }
+
+    /// Sets the URL the response was finally received from, after redirects.
+    pub fn with_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    fn from_header_map(
+        status: u16,
+        header_map: &reqwest::header::HeaderMap,
+        body: Vec<u8>,
+    ) -> Self {
+        let headers = header_map
+            .iter()
+            .filter_map(|(k, v)| Some((k.as_str().to_string(), v.to_str().ok()?.to_string())))
+            .collect();
+        Self::new(status, headers, body)
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn from_reqwest(response: reqwest::Response) -> Result<Self, ToolsError> {
+        let status = response.status().as_u16();
+        let header_map = response.headers().to_owned();
+        let url = response.url().to_string();
+        let body = response.bytes().await?.to_vec();
+        Ok(Self::from_header_map(status, &header_map, body).with_url(url))
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn from_reqwest_blocking(
+        response: reqwest::blocking::Response,
+    ) -> Result<Self, ToolsError> {
+        let status = response.status().as_u16();
+        let header_map = response.headers().to_owned();
+        let url = response.url().to_string();
+        let body = response.bytes()?.to_vec();
+        Ok(Self::from_header_map(status, &header_map, body).with_url(url))
+    }
+
+    /// Returns the HTTP status code.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Returns `true` for 2xx status codes.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Returns the value of a header (case-insensitive), if present.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers
+            .iter()
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Returns the URL the response was finally received from, after redirects, if known.
+    /// Responses from caches and fixtures have no URL.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Returns the body as text, replacing invalid UTF-8.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+
+    /// Returns the response if its status is 2xx, otherwise a `RateLimited` (for 429) or `HttpStatus` error.
+    /// `tool` and `url` are used to describe the error.
+    pub fn error_for_status(self, tool: &str, url: &str) -> Result<Self, ToolsError> {
+        match self.status {
+            _ if self.is_success() => Ok(self),
+            429 => Err(ToolsError::RateLimited {
+                url: url.to_string(),
+                retry_after: self
+                    .header("retry-after")
+                    .and_then(RetryPolicy::parse_retry_after),
+            }),
+            status => Err(ToolsError::http_status(tool, url, status, &self.text())),
+        }
+    }
+
+    /// Parses the body as JSON.
+    pub fn json(&self) -> Result<Value, ToolsError> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redacted() {
+        let request = ToolRequest::post("https://example.org/api.php?token=abc&foo=bar")
+            .query(&[("password".to_string(), "secret".to_string())])
+            .form(vec![
+                ("token".to_string(), "abc".to_string()),
+                ("data".to_string(), "Q42".to_string()),
+            ]);
+        let redacted = request.redacted();
+        assert_eq!(
+            redacted.full_url(),
+            "https://example.org/api.php?token=REDACTED&foo=bar&password=REDACTED"
+        );
+        assert_eq!(
+            redacted.body(),
+            &RequestBody::Form(vec![
+                ("token".to_string(), "REDACTED".to_string()),
+                ("data".to_string(), "Q42".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tool_response() {
+        let response = ToolResponse::new(
+            200,
+            vec![("Retry-After".to_string(), "5".to_string())],
+            br#"{"status":"OK"}"#.to_vec(),
+        );
+        assert!(response.is_success());
+        assert_eq!(response.header("retry-after"), Some("5"));
+        assert_eq!(response.json().unwrap(), json!({"status":"OK"}));
+
+        let response = ToolResponse::new(500, vec![], b"Oops".to_vec());
+        match response.error_for_status("PetScan", "https://example.org") {
+            Err(ToolsError::HttpStatus {
+                status,
+                body_excerpt,
+                ..
+            }) => {
+                assert_eq!(status, 500);
+                assert_eq!(body_excerpt, "Oops");
+            }
+            other => panic!("Expected HttpStatus, got {other:?}"),
+        }
+    }
+}
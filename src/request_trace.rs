@@ -0,0 +1,195 @@
+/// # Request tracing
+/// With the `tracing` feature, every request sent by a `ToolsInterface` gets a `tool_request` span,
+/// with the tool name, method and URL (secret parameters redacted, see `ToolRequest::redacted`).
+/// Each attempt emits a `DEBUG` event with its status, latency and response size;
+/// the final result emits an `INFO` event (or `WARN` on failure) with the number of retries and the total latency.
+/// Creating a MediaWiki `Api` object, which fetches the site info, gets a `mediawiki_api` span.
+/// Without the feature, all of this compiles to nothing.
+///
+/// ## Example
+/// ```ignore
+/// tracing_subscriber::fmt()
+///     .with_max_level(tracing::Level::DEBUG)
+///     .with_writer(std::io::stderr)
+///     .init();
+/// let mut ps = PetScan::new(25951472);
+/// ps.run().await.unwrap(); // Logs the request to PetScan
+/// ```
+use crate::{ToolRequest, ToolResponse, ToolsError};
+use std::time::Duration;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+pub(crate) struct RequestTrace {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    start: Instant,
+}
+
+impl RequestTrace {
+    /// Starts tracing a request. `tool` is only called if tracing is enabled.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn start<F: FnOnce() -> String>(request: &ToolRequest, tool: F) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "tool_request",
+                tool = %tool(),
+                method = %request.method(),
+                url = %request.redacted().full_url(),
+            ),
+            #[cfg(feature = "tracing")]
+            start: Instant::now(),
+        }
+    }
+
+    /// The response came from the cache.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn cached(&self, response: &ToolResponse) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            parent: &self.span,
+            status = response.status(),
+            bytes = response.body().len(),
+            "cached response"
+        );
+    }
+
+    /// One attempt to send the request has finished, after `latency`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn attempt(
+        &self,
+        attempt: u32,
+        latency: Duration,
+        result: &Result<ToolResponse, ToolsError>,
+    ) {
+        #[cfg(feature = "tracing")]
+        match result {
+            Ok(response) => tracing::debug!(
+                parent: &self.span,
+                attempt,
+                status = response.status(),
+                latency_ms = latency.as_millis() as u64,
+                bytes = response.body().len(),
+                "attempt finished"
+            ),
+            Err(e) => tracing::debug!(
+                parent: &self.span,
+                attempt,
+                latency_ms = latency.as_millis() as u64,
+                error = %e,
+                "attempt failed"
+            ),
+        }
+    }
+
+    /// The request has finished, after `attempts` attempts.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn finish(&self, attempts: u32, result: &Result<ToolResponse, ToolsError>) {
+        #[cfg(feature = "tracing")]
+        {
+            let retries = attempts.saturating_sub(1);
+            let latency_ms = self.start.elapsed().as_millis() as u64;
+            match result {
+                Ok(response) if response.is_success() => tracing::info!(
+                    parent: &self.span,
+                    status = response.status(),
+                    retries,
+                    latency_ms,
+                    bytes = response.body().len(),
+                    "request finished"
+                ),
+                Ok(response) => tracing::warn!(
+                    parent: &self.span,
+                    status = response.status(),
+                    retries,
+                    latency_ms,
+                    bytes = response.body().len(),
+                    "request finished with error status"
+                ),
+                Err(e) => tracing::warn!(
+                    parent: &self.span,
+                    retries,
+                    latency_ms,
+                    error = %e,
+                    "request failed"
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+/// Traces the creation of a MediaWiki `Api` object for `api_url`.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) async fn trace_api<T, F: Future<Output = T>>(api_url: &str, future: F) -> T {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
+        let start = Instant::now();
+        let span = tracing::info_span!("mediawiki_api", url = %api_url);
+        let ret = future.instrument(span.clone()).await;
+        tracing::info!(
+            parent: &span,
+            latency_ms = start.elapsed().as_millis() as u64,
+            "api created"
+        );
+        ret
+    }
+    #[cfg(not(feature = "tracing"))]
+    future.await
+}
+
+#[cfg(all(test, feature = "tracing", feature = "blocking"))]
+mod tests {
+    use super::*;
+    use crate::ToolsInterface;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use wiremock::matchers::query_param;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_request_trace() {
+        let server = futures::executor::block_on(MockServer::start());
+        futures::executor::block_on(
+            Mock::given(query_param("token", "secret"))
+                .respond_with(ResponseTemplate::new(200).set_body_string("OK"))
+                .mount(&server),
+        );
+        let ti = ToolsInterface::builder().build().unwrap();
+        let request = ToolRequest::get(format!("{}/api.php", server.uri()))
+            .query(&[("token".to_string(), "secret".to_string())]);
+
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            ti.execute_blocking(&request).unwrap();
+        });
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("attempt finished"));
+        assert!(output.contains("request finished"));
+        assert!(output.contains("token=REDACTED"));
+        assert!(!output.contains("secret"));
+    }
+}
@@ -0,0 +1,281 @@
+/// # Retry
+/// `RetryPolicy` decides if and when a failed request is sent again.
+/// It is part of a `ToolsInterface`, and applies to every request sent through it, async or blocking.
+/// Delays grow exponentially from `initial_backoff` up to `max_backoff`, with optional random jitter.
+/// A `Retry-After` header sent by the server takes precedence over the computed delay, up to `max_backoff`.
+/// Only idempotent requests (e.g. GET) are retried, unless `with_retry_non_idempotent` is set;
+/// a POST like a QuickStatements import might otherwise be applied twice.
+///
+/// ## Example
+/// ```ignore
+/// let policy = RetryPolicy::default()
+///     .with_max_attempts(10)
+///     .with_retryable_statuses(&[429, 503]);
+/// let ti = ToolsInterface::builder().retry_policy(policy).build().unwrap();
+/// ```
+use crate::ToolsError;
+use crate::request::{ToolRequest, ToolResponse};
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_INITIAL_BACKOFF_MS: u64 = 1000;
+const DEFAULT_MAX_BACKOFF_SECONDS: u64 = 60;
+const DEFAULT_RETRYABLE_STATUSES: [u16; 4] = [429, 502, 503, 504];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    respect_retry_after: bool,
+    retry_network_errors: bool,
+    retry_non_idempotent: bool,
+    retryable_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: Duration::from_millis(DEFAULT_INITIAL_BACKOFF_MS),
+            max_backoff: Duration::from_secs(DEFAULT_MAX_BACKOFF_SECONDS),
+            jitter: true,
+            respect_retry_after: true,
+            retry_network_errors: false,
+            retry_non_idempotent: false,
+            retryable_statuses: DEFAULT_RETRYABLE_STATUSES.to_vec(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    /// Total number of attempts, including the first one. Values below 1 are treated as 1.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry. Doubles with every further retry.
+    pub fn with_initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Upper limit for the computed delay, and for the delay requested via `Retry-After`.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Randomize delays (between 50% and 100% of the computed value), to spread out concurrent retries.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Use the `Retry-After` header, if the server sends one.
+    pub fn with_respect_retry_after(mut self, respect_retry_after: bool) -> Self {
+        self.respect_retry_after = respect_retry_after;
+        self
+    }
+
+    /// Also retry on connection errors and timeouts.
+    pub fn with_retry_network_errors(mut self, retry_network_errors: bool) -> Self {
+        self.retry_network_errors = retry_network_errors;
+        self
+    }
+
+    /// Also retry requests with a non-idempotent method, like POST.
+    /// Only use this if all requests sent through the `ToolsInterface` are safe to repeat.
+    pub fn with_retry_non_idempotent(mut self, retry_non_idempotent: bool) -> Self {
+        self.retry_non_idempotent = retry_non_idempotent;
+        self
+    }
+
+    /// HTTP status codes that trigger a retry.
+    pub fn with_retryable_statuses(mut self, statuses: &[u16]) -> Self {
+        self.retryable_statuses = statuses.to_vec();
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub fn retryable_statuses(&self) -> &[u16] {
+        &self.retryable_statuses
+    }
+
+    /// Returns `true` if a response with this status should be retried.
+    pub fn is_retryable_status(&self, status: u16) -> bool {
+        self.retryable_statuses.contains(&status)
+    }
+
+    /// Returns `true` if a request may be sent again.
+    pub fn is_retryable_request(&self, request: &ToolRequest) -> bool {
+        self.retry_non_idempotent || request.method().is_idempotent()
+    }
+
+    /// Returns the delay before the next attempt, or `None` if `result` of attempt number `attempt` (starting at 1)
+    /// of `request` is final.
+    pub fn retry_delay(
+        &self,
+        request: &ToolRequest,
+        attempt: u32,
+        result: &Result<ToolResponse, ToolsError>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.is_retryable_request(request) {
+            return None;
+        }
+        match result {
+            Ok(response) => {
+                if !self.is_retryable_status(response.status()) {
+                    return None;
+                }
+                let retry_after = match self.respect_retry_after {
+                    true => response
+                        .header("retry-after")
+                        .and_then(Self::parse_retry_after)
+                        .map(|retry_after| retry_after.min(self.max_backoff)),
+                    false => None,
+                };
+                Some(retry_after.unwrap_or_else(|| self.backoff(attempt)))
+            }
+            Err(ToolsError::Reqwest(e)) if self.retry_network_errors => {
+                match e.is_timeout() || e.is_connect() {
+                    true => Some(self.backoff(attempt)),
+                    false => None,
+                }
+            }
+            Err(ToolsError::Timeout { .. }) if self.retry_network_errors => {
+                Some(self.backoff(attempt))
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Exponential backoff for the given attempt (starting at 1), capped and optionally jittered.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        match self.jitter {
+            true => delay.mul_f64(0.5 + Self::random_fraction() / 2.0),
+            false => delay,
+        }
+    }
+
+    /// `Retry-After` is either a number of seconds, or an HTTP date.
+    pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+        let value = value.trim();
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+        let date = DateTime::parse_from_rfc2822(value).ok()?;
+        let seconds = (date.with_timezone(&Utc) - Utc::now()).num_seconds().max(0);
+        Some(Duration::from_secs(seconds as u64))
+    }
+
+    /// A random number in [0,1), good enough for jitter.
+    fn random_fraction() -> f64 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        (hasher.finish() % 1_000_000) as f64 / 1_000_000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, retry_after: Option<&str>) -> Result<ToolResponse, ToolsError> {
+        let headers = retry_after
+            .map(|v| vec![("Retry-After".to_string(), v.to_string())])
+            .unwrap_or_default();
+        Ok(ToolResponse::new(status, headers, vec![]))
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let get = ToolRequest::get("https://example.org/api.php");
+        let policy = RetryPolicy::default()
+            .with_jitter(false)
+            .with_max_attempts(3);
+        assert_eq!(policy.retry_delay(&get, 1, &response(200, None)), None);
+        assert_eq!(policy.retry_delay(&get, 1, &response(404, None)), None);
+        assert_eq!(
+            policy.retry_delay(&get, 1, &response(503, None)),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            policy.retry_delay(&get, 2, &response(503, None)),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            policy.retry_delay(&get, 1, &response(429, Some("7"))),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(policy.retry_delay(&get, 3, &response(503, None)), None);
+        assert_eq!(
+            RetryPolicy::none().retry_delay(&get, 1, &response(503, None)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_retry_after_capped() {
+        let get = ToolRequest::get("https://example.org/api.php");
+        let policy = RetryPolicy::default().with_max_backoff(Duration::from_secs(30));
+        assert_eq!(
+            policy.retry_delay(&get, 1, &response(429, Some("86400"))),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            policy.retry_delay(
+                &get,
+                1,
+                &response(429, Some("Wed, 21 Oct 2099 07:28:00 GMT"))
+            ),
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn test_retry_non_idempotent() {
+        let post = ToolRequest::post("https://quickstatements.toolforge.org/api.php");
+        let policy = RetryPolicy::default().with_jitter(false);
+        assert!(!policy.is_retryable_request(&post));
+        assert_eq!(policy.retry_delay(&post, 1, &response(503, None)), None);
+        let policy = policy.with_retry_non_idempotent(true);
+        assert_eq!(
+            policy.retry_delay(&post, 1, &response(503, None)),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_backoff_jitter_and_cap() {
+        let policy = RetryPolicy::default().with_max_backoff(Duration::from_secs(4));
+        for attempt in 1..10 {
+            let delay = policy.backoff(attempt);
+            assert!(delay <= Duration::from_secs(4));
+            assert!(delay >= Duration::from_millis(500));
+        }
+    }
+}
@@ -15,11 +15,17 @@
 ///        println!("Page {} Item {} Description {}", result.title, result.qid, result.description);
 ///     });
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    NamespaceTable, PageListSource, Paginated, ResultPage, Site, Tool, ToolRequest, ToolResponse,
+    ToolsError, ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
+use mediawiki::title::Title;
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct WikiSearchResult {
     pub namespace_id: u32,
     pub title: String,
@@ -42,7 +48,8 @@ impl WikiSearchResult {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WikiSearch {
     site: Site,
     query: String,
@@ -106,61 +113,120 @@ impl WikiSearch {
     pub fn limit(&self) -> u32 {
         self.limit
     }
-
-    pub async fn as_json(&self) -> Value {
-        let site = self.site();
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.results()
-                .iter()
-                .map(|result| (FancyTitle::new(&result.title, result.namespace_id as i64, &api).to_json(),result))
-                .map(|(mut v,result)| {
-                    v["page_id"] = json!(result.page_id);
-                    v["size"] = json!(result.size);
-                    v["wordcount"] = json!(result.wordcount);
-                    v["snippet"] = json!(result.snippet);
-                    v
-                })
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
-    }
 }
 
 #[async_trait]
 impl Tool for WikiSearch {
-    fn get_url(&self) -> String {
+    type Output = Vec<WikiSearchResult>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        Ok(ToolRequest::get(self.get_url(ti)))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.results.clone())
+    }
+}
+
+impl WikiSearch {
+    pub fn get_url(&self, _ti: &ToolsInterface) -> String {
+        self.url_at_offset(self.offset)
+    }
+
+    fn url_at_offset(&self, offset: u32) -> String {
         format!(
             "https://{server}/w/api.php?action=query&list=search&srsearch={query}&srnamespace={namespace_id}&sroffset={offset}&srlimit={limit}&format=json",
             server = self.site.webserver(),
             query = self.query,
             namespace_id = self.namespace_ids,
-            offset = self.offset,
             limit = self.limit,
         )
     }
 
     fn set_from_json(&mut self, j: Value) -> Result<(), ToolsError> {
-        self.results = j["query"]["search"]
+        self.results = Self::results_from_json(&j)?;
+        Ok(())
+    }
+
+    fn results_from_json(j: &Value) -> Result<Vec<WikiSearchResult>, ToolsError> {
+        Ok(j["query"]["search"]
             .as_array()
-            .ok_or_else(|| ToolsError::Json("Result is not an array".to_string()))?
+            .ok_or_else(|| ToolsError::parse("WikiSearch", "query.search", "not an array"))?
             .iter()
             .filter_map(WikiSearchResult::from_json)
+            .collect())
+    }
+}
+
+/// Pages through the search results, `limit` results per request, starting at `offset`.
+impl Paginated for WikiSearch {
+    type Item = WikiSearchResult;
+    type Cursor = u32;
+
+    fn page_request(
+        &self,
+        _ti: &ToolsInterface,
+        cursor: Option<&u32>,
+    ) -> Result<ToolRequest, ToolsError> {
+        Ok(ToolRequest::get(
+            self.url_at_offset(cursor.copied().unwrap_or(self.offset)),
+        ))
+    }
+
+    fn parse_page(
+        &self,
+        response: &ToolResponse,
+        _cursor: Option<&u32>,
+    ) -> Result<ResultPage<WikiSearchResult, u32>, ToolsError> {
+        let j = response.json()?;
+        Ok(ResultPage {
+            items: Self::results_from_json(&j)?,
+            next: j["continue"]["sroffset"]
+                .as_u64()
+                .map(|offset| offset as u32),
+        })
+    }
+}
+
+impl PageListSource for WikiSearch {
+    fn page_list_site(&self) -> Option<Site> {
+        Some(self.site.clone())
+    }
+
+    fn page_list_with(&self, _namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let pages = self
+            .results
+            .iter()
+            .map(|result| {
+                let title = Title::new(&result.title, result.namespace_id as i64);
+                let meta = PageMeta {
+                    page_id: Some(result.page_id as u64),
+                    size: result.size.map(|size| size as u64),
+                    ..Default::default()
+                }
+                .with_extra("wordcount", json!(result.wordcount))
+                .with_extra("snippet", json!(result.snippet));
+                Page::new(title, meta)
+            })
             .collect();
-        Ok(())
+        Ok(PageList::new(self.site.clone(), pages))
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "tokio"))]
 mod tests {
     use super::*;
+    use crate::replay::Replay;
 
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_search_json() {
         let site = Site::from_wiki("enwiki").unwrap();
         let query = "Heinrich Magnus Manske";
+        let replay = Replay::start("search_heinrich_magnus_manske").await;
         let mut tool = WikiSearch::new(site, query);
-        tool.run().await.unwrap();
+        tool.run_with(&replay).await.unwrap();
         assert!(
             tool.results()
                 .iter()
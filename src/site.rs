@@ -1,13 +1,21 @@
 /// # Site
 /// `Site` is a struct that represents a MediaWiki site.
-/// It can be created from a wiki name, or a language and project.
+/// It can be created from a wiki name, a language and project, a webserver, or a URL.
+/// When deserialized, a plain wiki name (e.g. "dewiki") can be used instead of all fields.
+/// Wiki names and webservers are looked up in the global `SiteRegistry` first, and guessed from name patterns otherwise.
 /// It provides methods to get the language, project, and webserver of the site,
 /// as well as a MediaWiki `Api` object.
 use lazy_static::lazy_static;
+#[cfg(feature = "tokio")]
 use mediawiki::api::Api;
 use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "tokio")]
+use std::sync::Arc;
 
+use crate::SiteRegistry;
+#[cfg(feature = "tokio")]
 use crate::ToolsError;
 
 lazy_static! {
@@ -16,11 +24,82 @@ lazy_static! {
     static ref RE_WEBSERVER_WIKI: Regex = Regex::new(r"^(.+)(wik.+)$").expect("Regex error");
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+/// Sites are equal if their wiki names are, however they were created.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(try_from = "SiteDef")]
 pub struct Site {
     wiki: String,
     language: String,
     project: String,
+    webserver: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    closed: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    private: bool,
+}
+
+impl PartialEq for Site {
+    fn eq(&self, other: &Self) -> bool {
+        self.wiki == other.wiki
+    }
+}
+
+impl Eq for Site {}
+
+impl Hash for Site {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.wiki.hash(state);
+    }
+}
+
+/// A `Site` is deserialized either from all its fields, or from just a wiki name, e.g. "dewiki".
+/// Without a webserver, it is guessed from language and project.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SiteDef {
+    Wiki(String),
+    Fields {
+        wiki: String,
+        language: String,
+        project: String,
+        #[serde(default)]
+        webserver: String,
+        #[serde(default)]
+        closed: bool,
+        #[serde(default)]
+        private: bool,
+    },
+}
+
+impl TryFrom<SiteDef> for Site {
+    type Error = String;
+
+    fn try_from(def: SiteDef) -> Result<Self, Self::Error> {
+        match def {
+            SiteDef::Wiki(wiki) => Self::from_wiki(&wiki).ok_or(format!("Unknown wiki {wiki}")),
+            SiteDef::Fields {
+                wiki,
+                language,
+                project,
+                webserver,
+                closed,
+                private,
+            } => {
+                let webserver = match webserver.is_empty() {
+                    true => Self::guess_webserver(&language, &project),
+                    false => webserver,
+                };
+                Ok(Self {
+                    wiki,
+                    language,
+                    project,
+                    webserver,
+                    closed,
+                    private,
+                })
+            }
+        }
+    }
 }
 
 impl Site {
@@ -28,6 +107,9 @@ impl Site {
     /// Returns `None` if the wiki name is not recognized.
     pub fn from_wiki(wiki: &str) -> Option<Self> {
         let wiki = Self::normalize_wiki(wiki);
+        if let Some(site) = SiteRegistry::global().site(&wiki) {
+            return Some(site.to_owned());
+        }
         let language;
         let mut project;
         match wiki.as_str() {
@@ -63,13 +145,68 @@ impl Site {
             }
         }
 
+        let webserver = Self::guess_webserver(&language, &project);
         Some(Self {
             wiki,
             language,
             project,
+            webserver,
+            ..Default::default()
         })
     }
 
+    /// Creates a new `Site` object from a webserver, e.g. "en.wikipedia.org".
+    /// Returns `None` if the webserver is not recognized.
+    pub fn from_webserver(webserver: &str) -> Option<Self> {
+        let webserver = webserver.trim().trim_end_matches('.').to_ascii_lowercase();
+        if let Some(site) = SiteRegistry::global().site_for_webserver(&webserver) {
+            return Some(site.to_owned());
+        }
+        let parts: Vec<&str> = webserver.split('.').collect();
+        match parts.as_slice() {
+            [language, project, "org"] if !language.is_empty() && !project.is_empty() => Some(
+                Self::from_language_project(&language.replace('-', "_"), project),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Creates a new `Site` object from a URL, e.g. "https://en.wikipedia.org/wiki/Foo".
+    /// Returns `None` if the URL can not be parsed, or its webserver is not recognized.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let url = match url.contains("://") {
+            true => reqwest::Url::parse(url).ok()?,
+            false => reqwest::Url::parse(&format!("https://{url}")).ok()?,
+        };
+        Self::from_webserver(url.host_str()?)
+    }
+
+    /// Creates a `Site` from a sitematrix entry.
+    /// `code` is used as language if the webserver has no language subdomain.
+    /// Like everywhere else, multi-part language codes use underscores, e.g. "zh_min_nan".
+    pub(crate) fn from_parts(
+        wiki: &str,
+        code: &str,
+        webserver: &str,
+        closed: bool,
+        private: bool,
+    ) -> Self {
+        let parts: Vec<&str> = webserver.split('.').collect();
+        let (language, project) = match parts.as_slice() {
+            [.., language, project, _tld] => (language.replace('-', "_"), project.to_string()),
+            [project, _tld] => (code.to_string(), project.to_string()),
+            _ => (code.to_string(), String::new()),
+        };
+        Self {
+            wiki: wiki.to_string(),
+            language,
+            project,
+            webserver: webserver.to_string(),
+            closed,
+            private,
+        }
+    }
+
     /// Creates a new `Site` object from a language and project.
     pub fn from_language_project(language: &str, project: &str) -> Self {
         let wiki = match project {
@@ -82,6 +219,8 @@ impl Site {
             wiki,
             language: language.to_string(),
             project: project.to_string(),
+            webserver: Self::guess_webserver(language, project),
+            ..Default::default()
         }
     }
 
@@ -89,23 +228,35 @@ impl Site {
         wiki.replace("-", "_").trim().to_ascii_lowercase()
     }
 
-    /// Returns "language.project", with language having "-" instead of "_".
-    /// Useful for `Pageviews``.
-    pub fn language_project(&self) -> String {
+    fn guess_webserver(language: &str, project: &str) -> String {
         format!(
-            "{language}.{project}",
-            language = self.language.replace('_', "-"),
-            project = self.project
+            "{language}.{project}.org",
+            language = language.replace('_', "-"),
         )
     }
 
+    /// Returns the webserver without top-level domain, e.g. "en.wikipedia".
+    /// Useful for `Pageviews``.
+    pub fn language_project(&self) -> String {
+        match self.webserver.rsplit_once('.') {
+            Some((language_project, _tld)) => language_project.to_string(),
+            None => self.webserver.to_owned(),
+        }
+    }
+
     /// Returns the webserver for the site, e.g. "en.wikipedia.org".
     pub fn webserver(&self) -> String {
-        format!(
-            "{language}.{project}.org",
-            language = self.language.replace('_', "-"),
-            project = self.project
-        )
+        self.webserver.to_owned()
+    }
+
+    /// Returns `true` if the wiki is closed (read-only).
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Returns `true` if the wiki is private.
+    pub fn is_private(&self) -> bool {
+        self.private
     }
 
     /// Returns the wiki name, e.g. "enwiki".
@@ -123,14 +274,18 @@ impl Site {
         &self.project
     }
 
-    /// Returns a MediaWiki `Api` object for the site.
-    pub async fn api(&self) -> Result<Api, ToolsError> {
-        let api_url = format!(
+    /// Returns the URL of the MediaWiki API, e.g. "https://en.wikipedia.org/w/api.php".
+    pub fn api_url(&self) -> String {
+        format!(
             "https://{webserver}/w/api.php",
             webserver = self.webserver()
-        );
-        let api = Api::new(&api_url).await?;
-        Ok(api)
+        )
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Returns the MediaWiki `Api` object for the site, shared via the process-wide `ToolsInterface`.
+    pub async fn api(&self) -> Result<Arc<Api>, ToolsError> {
+        crate::ToolsInterface::global().api(self).await
     }
 }
 
@@ -171,6 +326,92 @@ mod tests {
         assert_eq!(site.project, "wikimedia");
     }
 
+    #[test]
+    fn test_site_from_webserver_and_url() {
+        let site = Site::from_wiki("be-x-oldwiki").unwrap();
+        assert_eq!(site.webserver(), "be-tarask.wikipedia.org");
+        assert_eq!(
+            Site::from_webserver("be-tarask.wikipedia.org").unwrap(),
+            site
+        );
+
+        let site = Site::from_wiki("sourceswiki").unwrap();
+        assert_eq!(site.webserver(), "wikisource.org");
+        assert_eq!(site.language_project(), "wikisource");
+
+        let site = Site::from_url("https://test.wikidata.org/wiki/Q1").unwrap();
+        assert_eq!(site.wiki(), "testwikidatawiki");
+
+        let site = Site::from_url("fr.wikipedia.org/wiki/Paris").unwrap();
+        assert_eq!(site.wiki(), "frwiki");
+        assert_eq!(site.language(), "fr");
+        assert!(!site.is_closed());
+        assert!(Site::from_wiki("aawiki").unwrap().is_closed());
+        assert!(Site::from_webserver("example.com").is_none());
+    }
+
+    #[test]
+    fn test_site_multi_part_language() {
+        let site = Site::from_wiki("zh_min_nanwiki").unwrap();
+        assert_eq!(site.language(), "zh_min_nan");
+        assert_eq!(site.webserver(), "zh-min-nan.wikipedia.org");
+        let site = Site::from_webserver("roa-tara.wikipedia.org").unwrap();
+        assert_eq!(site.wiki(), "roa_tarawiki");
+        assert_eq!(site.language(), "roa_tara");
+        assert_eq!(
+            Site::from_wiki("be_x_oldwiki").unwrap().language(),
+            "be_tarask"
+        );
+        // Wikis not in the registry follow the same convention
+        assert_eq!(Site::from_wiki("xx_yywiki").unwrap().language(), "xx_yy");
+        assert_eq!(
+            Site::from_webserver("xx-yy.wikipedia.org")
+                .unwrap()
+                .language(),
+            "xx_yy"
+        );
+    }
+
+    #[test]
+    fn test_site_eq() {
+        use std::collections::HashSet;
+
+        // Registry sites equal the guessed ones for the same wiki
+        let registry = Site::from_wiki("be_x_oldwiki").unwrap();
+        let guessed = Site::from_language_project("be_x_old", "wikipedia");
+        assert_ne!(registry.webserver(), guessed.webserver());
+        assert_eq!(registry, guessed);
+        let closed = Site::from_wiki("aawiki").unwrap();
+        assert!(closed.is_closed());
+        assert_eq!(closed, Site::from_language_project("aa", "wikipedia"));
+        let set: HashSet<Site> = [registry, guessed, closed].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        assert_ne!(
+            Site::from_wiki("enwiki").unwrap(),
+            Site::from_wiki("dewiki").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_site_deserialize() {
+        let site: Site = serde_json::from_str("\"dewiki\"").unwrap();
+        assert_eq!(site, Site::from_wiki("dewiki").unwrap());
+        let site2: Site = serde_json::from_value(serde_json::to_value(&site).unwrap()).unwrap();
+        assert_eq!(site, site2);
+        assert!(serde_json::from_str::<Site>("\"\"").is_err());
+
+        let site: Site = serde_json::from_str(
+            r#"{"wiki":"enwiktionarywiki","language":"en","project":"wiktionary"}"#,
+        )
+        .unwrap();
+        assert_eq!(site.webserver(), "en.wiktionary.org");
+        let site: Site = serde_json::from_str(
+            r#"{"wiki":"zh_min_nanwiki","language":"zh_min_nan","project":"wikipedia","webserver":""}"#,
+        )
+        .unwrap();
+        assert_eq!(site.webserver(), "zh-min-nan.wikipedia.org");
+    }
+
     #[test]
     fn test_site_from_language_project() {
         let site = Site::from_language_project("en", "wikipedia");
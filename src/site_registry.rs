@@ -0,0 +1,255 @@
+/// # Site registry
+/// `SiteRegistry` maps wiki database names (e.g. "be_x_oldwiki") to their webservers (e.g. "be-tarask.wikipedia.org") and back.
+/// It is read from the output of the MediaWiki [sitematrix API](https://meta.wikimedia.org/w/api.php?action=sitematrix),
+/// and also knows which wikis are closed or private.
+/// A snapshot is bundled with this crate; it covers all language editions of Wikipedia, the sister projects,
+/// and the special wikis. It is refreshed with `ti sitematrix > data/sitematrix.json`.
+/// Wikis not in the registry, e.g. ones created after the snapshot, are resolved by name patterns, see `Site::from_wiki`.
+/// A complete registry can be fetched from the live API, and installed as the process-wide registry.
+///
+/// ## Example
+/// ```ignore
+/// let registry = SiteRegistry::fetch(&ToolsInterface::global()).await?;
+/// SiteRegistry::set_global(registry);
+/// let site = Site::from_wiki("be_x_oldwiki").unwrap();
+/// assert_eq!(site.webserver(), "be-tarask.wikipedia.org");
+/// ```
+use crate::{Endpoint, Site, ToolRequest, ToolsError, ToolsInterface};
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, RwLock};
+
+/// Sitematrix snapshot, in the format of the sitematrix API.
+const BUNDLED_SITEMATRIX: &str = include_str!("../data/sitematrix.json");
+
+lazy_static! {
+    static ref GLOBAL_SITE_REGISTRY: RwLock<Arc<SiteRegistry>> =
+        RwLock::new(Arc::new(SiteRegistry::bundled()));
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SiteRegistry {
+    sites: HashMap<String, Site>,
+    webserver2wiki: HashMap<String, String>,
+}
+
+impl SiteRegistry {
+    /// Returns the registry from the sitematrix snapshot bundled with this crate.
+    pub fn bundled() -> Self {
+        let json: Value = serde_json::from_str(BUNDLED_SITEMATRIX)
+            .expect("Bundled sitematrix should be valid JSON");
+        Self::from_json(&json).expect("Bundled sitematrix should be valid")
+    }
+
+    /// Reads a registry from sitematrix JSON.
+    /// The JSON can be the complete API response, or its `sitematrix` part.
+    pub fn from_json(json: &Value) -> Result<Self, ToolsError> {
+        let json = match json.get("sitematrix") {
+            Some(sitematrix) => sitematrix,
+            None => json,
+        };
+        let groups = json
+            .as_object()
+            .ok_or_else(|| ToolsError::parse("SiteMatrix", "sitematrix", "not an object"))?;
+        let mut ret = Self::default();
+        for (key, group) in groups {
+            match key.as_str() {
+                "count" => {}
+                "specials" => {
+                    for site in group.as_array().into_iter().flatten() {
+                        let language = site["code"].as_str().unwrap_or_default();
+                        ret.add_from_json(site, language)?;
+                    }
+                }
+                _ => {
+                    let language = group["code"].as_str().unwrap_or_default();
+                    for site in group["site"].as_array().into_iter().flatten() {
+                        ret.add_from_json(site, language)?;
+                    }
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Reads a registry from a sitematrix JSON file.
+    pub fn from_file(filename: &str) -> Result<Self, ToolsError> {
+        let file = File::open(filename)?;
+        let reader = BufReader::new(file);
+        let json: Value = serde_json::from_reader(reader)?;
+        Self::from_json(&json)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Fetches the complete registry from the sitematrix API.
+    pub async fn fetch(ti: &ToolsInterface) -> Result<Self, ToolsError> {
+        Self::from_json(&Self::fetch_json(ti).await?)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Fetches the complete registry from the sitematrix API, in a blocking manner.
+    pub fn fetch_blocking(ti: &ToolsInterface) -> Result<Self, ToolsError> {
+        Self::from_json(&Self::fetch_json_blocking(ti)?)
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Fetches the raw sitematrix API response, e.g. to update the bundled snapshot.
+    pub async fn fetch_json(ti: &ToolsInterface) -> Result<Value, ToolsError> {
+        ti.execute_checked(&Self::request(ti)).await?.json()
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Fetches the raw sitematrix API response, in a blocking manner.
+    pub fn fetch_json_blocking(ti: &ToolsInterface) -> Result<Value, ToolsError> {
+        ti.execute_checked_blocking(&Self::request(ti))?.json()
+    }
+
+    /// Returns the process-wide registry, used by `Site::from_wiki` and friends.
+    pub fn global() -> Arc<Self> {
+        GLOBAL_SITE_REGISTRY
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replaces the process-wide registry.
+    pub fn set_global(registry: SiteRegistry) {
+        let mut global = GLOBAL_SITE_REGISTRY
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        *global = Arc::new(registry);
+    }
+
+    /// Returns the site for a wiki database name, e.g. "enwiki".
+    pub fn site(&self, wiki: &str) -> Option<&Site> {
+        self.sites.get(wiki)
+    }
+
+    /// Returns the site for a webserver, e.g. "en.wikipedia.org".
+    pub fn site_for_webserver(&self, webserver: &str) -> Option<&Site> {
+        let wiki = self.webserver2wiki.get(&webserver.to_ascii_lowercase())?;
+        self.sites.get(wiki)
+    }
+
+    /// Returns all sites in the registry.
+    pub fn sites(&self) -> impl Iterator<Item = &Site> {
+        self.sites.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+
+    fn request(ti: &ToolsInterface) -> ToolRequest {
+        let parameters = [("action", "sitematrix"), ("format", "json")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>();
+        ToolRequest::get(ti.endpoint(Endpoint::SiteMatrix)).query(&parameters)
+    }
+
+    fn add_from_json(&mut self, site: &Value, language: &str) -> Result<(), ToolsError> {
+        let wiki = site["dbname"]
+            .as_str()
+            .ok_or_else(|| ToolsError::parse("SiteMatrix", "dbname", "missing"))?;
+        let url = site["url"]
+            .as_str()
+            .ok_or_else(|| ToolsError::parse("SiteMatrix", "url", "missing"))?;
+        let webserver = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest)
+            .trim_end_matches('/')
+            .to_ascii_lowercase();
+        // Flags are present-but-empty in the default format, and booleans in formatversion=2
+        let flag = |key: &str| site.get(key).is_some_and(|v| v != &Value::Bool(false));
+        let site = Site::from_parts(wiki, language, &webserver, flag("closed"), flag("private"));
+        self.webserver2wiki.insert(webserver, wiki.to_string());
+        self.sites.insert(wiki.to_string(), site);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled() {
+        let registry = SiteRegistry::bundled();
+        let site = registry.site("be_x_oldwiki").unwrap();
+        assert_eq!(site.webserver(), "be-tarask.wikipedia.org");
+        assert_eq!(site.language(), "be_tarask");
+        assert_eq!(site.project(), "wikipedia");
+
+        let site = registry.site_for_webserver("wikisource.org").unwrap();
+        assert_eq!(site.wiki(), "sourceswiki");
+        assert_eq!(site.project(), "wikisource");
+
+        assert!(registry.site("strategywiki").unwrap().is_closed());
+        assert!(registry.site("officewiki").unwrap().is_private());
+        assert!(!registry.site("enwiki").unwrap().is_closed());
+    }
+
+    #[test]
+    fn test_bundled_real_wikis() {
+        let registry = SiteRegistry::bundled();
+        assert!(registry.len() > 800);
+        for (wiki, webserver) in [
+            ("enwiki", "en.wikipedia.org"),
+            ("frwiki", "fr.wikipedia.org"),
+            ("jawiki", "ja.wikipedia.org"),
+            ("simplewiki", "simple.wikipedia.org"),
+            ("kswiki", "ks.wikipedia.org"),
+            ("zh_min_nanwiki", "zh-min-nan.wikipedia.org"),
+            ("zh_yuewiki", "zh-yue.wikipedia.org"),
+            ("roa_tarawiki", "roa-tara.wikipedia.org"),
+            ("dewiktionary", "de.wiktionary.org"),
+            ("jawikisource", "ja.wikisource.org"),
+            ("ptwikivoyage", "pt.wikivoyage.org"),
+            ("guwwikinews", "guw.wikinews.org"),
+            ("commonswiki", "commons.wikimedia.org"),
+            ("mediawikiwiki", "www.mediawiki.org"),
+            ("labswiki", "wikitech.wikimedia.org"),
+            ("sewikimedia", "se.wikimedia.org"),
+        ] {
+            let site = registry.site(wiki).unwrap();
+            assert_eq!(site.webserver(), webserver, "{wiki}");
+            assert_eq!(registry.site_for_webserver(webserver).unwrap().wiki(), wiki);
+        }
+
+        let site = registry.site("zh_min_nanwiki").unwrap();
+        assert_eq!(site.language(), "zh_min_nan");
+        assert_eq!(site.project(), "wikipedia");
+        assert!(registry.site("chowiki").unwrap().is_closed());
+        assert!(!registry.site("frwiki").unwrap().is_closed());
+        assert!(registry.site("arbcom_enwiki").unwrap().is_private());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_fetch() {
+        use wiremock::matchers::query_param;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = r#"{"sitematrix":{"count":1,"0":{"code":"xx","site":[{"url":"https://xx.wikipedia.org","dbname":"xxwiki","code":"wiki","closed":true}]},"specials":[]}}"#;
+        Mock::given(query_param("action", "sitematrix"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::SiteMatrix, server.uri())
+            .build()
+            .unwrap();
+        let registry = SiteRegistry::fetch(&ti).await.unwrap();
+        assert_eq!(registry.len(), 1);
+        assert!(registry.site("xxwiki").unwrap().is_closed());
+    }
+}
@@ -15,12 +15,13 @@
 ///        println!("Entity changed: {}", entity_edit.id);
 ///     });
 /// ```
-use crate::{Tool, ToolsError};
+use crate::{Endpoint, Tool, ToolRequest, ToolResponse, ToolsError, ToolsInterface};
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct EntityEditor {
     pub id: u64,
     pub name: String,
@@ -37,7 +38,7 @@ impl EntityEditor {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct EntityEdit {
     pub id: String,
     pub label: String,
@@ -82,7 +83,8 @@ impl EntityEdit {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SparqlRC {
     sparql: String,
     start: Option<NaiveDateTime>,
@@ -91,7 +93,6 @@ pub struct SparqlRC {
     no_bot_edits: bool,
     skip_unchanged: bool,
 
-    tool_url: String,
     results: Vec<EntityEdit>,
 }
 
@@ -101,7 +102,6 @@ impl SparqlRC {
     pub fn new(sparql: &str) -> Self {
         Self {
             sparql: sparql.into(),
-            tool_url: "https://wikidata-todo.toolforge.org/sparql_rc.php".into(),
             ..Default::default()
         }
     }
@@ -157,39 +157,29 @@ impl SparqlRC {
 
 #[async_trait]
 impl Tool for SparqlRC {
-    #[cfg(feature = "tokio")]
-    /// Run the query asynchronously.
-    async fn run(&mut self) -> Result<(), ToolsError> {
+    type Output = Vec<EntityEdit>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
         self.check_start_date()?;
-        let url = &self.tool_url;
+        let url = format!("{}/sparql_rc.php", ti.endpoint(Endpoint::SparqlRC));
         let parameters = self.generate_paramters()?;
-        let client = crate::ToolsInterface::tokio_client()?;
-        let response = client.get(url).query(&parameters).send().await?;
-        let j: Value = response.json().await?;
-        self.set_from_json(j)
+        Ok(ToolRequest::get(url).query(&parameters))
     }
 
-    #[cfg(feature = "blocking")]
-    /// Run the query in a blocking manner.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        self.check_start_date()?;
-        let url = &self.tool_url;
-        let parameters = self.generate_paramters()?;
-        let client = crate::ToolsInterface::blocking_client()?;
-        let j: Value = client.get(url).query(&parameters).send()?.json()?;
-        self.set_from_json(j)
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.results.clone())
     }
+}
 
+impl SparqlRC {
     fn set_from_json(&mut self, j: Value) -> Result<(), ToolsError> {
         if j["status"].as_str() != Some("OK") {
-            return Err(ToolsError::Tool(format!(
-                "SparqlRC status is not OK: {:?}",
-                j["status"]
-            )));
+            return Err(ToolsError::tool_status("SparqlRC", j["status"].to_string()));
         }
         self.results = j["items"]
             .as_array()
-            .ok_or(ToolsError::Json("['items'] has no array".into()))?
+            .ok_or_else(|| ToolsError::parse("SparqlRC", "items", "not an array"))?
             .iter()
             .filter_map(EntityEdit::from_json)
             .collect();
@@ -197,9 +187,10 @@ impl Tool for SparqlRC {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "tokio"))]
 mod tests {
     use super::*;
+    use crate::replay::Replay;
     use chrono::NaiveDate;
     use std::fs::File;
     use wiremock::matchers::{method, path, query_param_contains};
@@ -230,17 +221,34 @@ mod tests {
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_sparql_rc_async() {
-        let mock_server = get_mock_server().await;
+        let replay = Replay::start("sparql_rc_q23413").await;
         let mut rc = SparqlRC::new("SELECT ?q { ?q wdt:P31 wd:Q23413 }")
             .start(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().into())
             .end(NaiveDate::from_ymd_opt(2024, 5, 2).unwrap().into());
-        rc.tool_url = format!("{}/sparql_rc.php", mock_server.uri());
-        rc.run().await.unwrap();
+        let results = rc.run_with(&replay).await.unwrap();
+        assert_eq!(results, rc.results());
         assert_eq!(rc.results().len(), 26);
         assert_eq!(rc.results()[0].id, "Q121134008");
         assert_eq!(rc.results()[0].label, "Castelluzzo");
         assert_eq!(rc.results()[0].editors.len(), 3);
     }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_sparql_rc_parameters() {
+        let mock_server = get_mock_server().await;
+        let mut rc = SparqlRC::new("SELECT ?q { ?q wdt:P31 wd:Q23413 }")
+            .start(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().into())
+            .end(NaiveDate::from_ymd_opt(2024, 5, 2).unwrap().into());
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::SparqlRC, mock_server.uri())
+            .build()
+            .unwrap();
+        rc.run_with(&ti).await.unwrap();
+        assert_eq!(rc.results().len(), 26);
+        assert_eq!(rc.results()[25].id, "Q14558523");
+        assert_eq!(rc.results()[25].label, "Little Castle");
+    }
 }
 
 // https://wikidata-todo.toolforge.org/sparql_rc.php?sparql=SELECT+%3Fq+{+%3Fq+wdt%3AP31+wd%3AQ23413+}&start=20240501&end=20240502&user_lang=&sort_mode=last_edit&no_bots=1&skip_unchanged=1&format=json
@@ -1,45 +1,56 @@
+/// # Tool
+/// The `Tool` trait is implemented by every tool in this crate.
+/// A tool describes the HTTP request it needs (`request`), and parses the response into a typed `Output` (`parse`).
+/// The provided `run*` methods send the request through a `ToolsInterface` and return that output.
+/// Tools also keep the results of the last run, available via their own accessors.
+///
+/// ## Example
+/// ```ignore
+/// async fn run_any<T: Tool>(tool: &mut T) -> Result<T::Output, ToolsError> {
+///     tool.run().await
+/// }
+/// ```
 use async_trait::async_trait;
-use serde_json::Value;
 
-use crate::ToolsError;
+use crate::{ToolRequest, ToolResponse, ToolsError, ToolsInterface};
 
 #[async_trait]
-pub trait Tool {
-    #[cfg(feature = "blocking")]
-    /// Run the tool in a blocking manner.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        let url = self.get_url();
-        let client = crate::ToolsInterface::blocking_client()?;
-        let json = client.get(&url).send()?.json()?;
-        self.set_from_json(json)
-    }
+pub trait Tool: Send {
+    /// The typed result of a run.
+    type Output: Send;
 
-    #[cfg(feature = "tokio")]
-    /// Run the tool asynchronously.
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = self.get_url();
-        let client = crate::ToolsInterface::tokio_client()?;
-        let json = client.get(&url).send().await?.json().await?;
-        self.set_from_json(json)
-    }
+    /// Describes the HTTP request that runs the tool.
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError>;
 
-    fn set_from_text(&mut self, _text: &str) -> Result<(), ToolsError> {
-        unimplemented!();
-    }
+    /// Parses the response of the request, stores the results in the tool, and returns them.
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError>;
 
-    fn set_from_json(&mut self, _j: Value) -> Result<(), ToolsError> {
-        unimplemented!();
+    #[cfg(feature = "blocking")]
+    /// Run the tool in a blocking manner, using the process-wide `ToolsInterface`.
+    fn run_blocking(&mut self) -> Result<Self::Output, ToolsError> {
+        self.run_blocking_with(&ToolsInterface::global())
     }
 
-    fn generate_payload(&self) -> Value {
-        unimplemented!();
+    #[cfg(feature = "blocking")]
+    /// Run the tool in a blocking manner, using the given `ToolsInterface`.
+    fn run_blocking_with(&mut self, ti: &ToolsInterface) -> Result<Self::Output, ToolsError> {
+        let request = self.request(ti)?;
+        let response = ti.execute_checked_blocking(&request)?;
+        self.parse(&response)
     }
 
-    fn generate_paramters(&self) -> Result<Vec<(String, String)>, ToolsError> {
-        unimplemented!();
+    #[cfg(feature = "tokio")]
+    /// Run the tool asynchronously, using the process-wide `ToolsInterface`.
+    async fn run(&mut self) -> Result<Self::Output, ToolsError> {
+        let ti = ToolsInterface::global();
+        self.run_with(&ti).await
     }
 
-    fn get_url(&self) -> String {
-        unimplemented!();
+    #[cfg(feature = "tokio")]
+    /// Run the tool asynchronously, using the given `ToolsInterface`.
+    async fn run_with(&mut self, ti: &ToolsInterface) -> Result<Self::Output, ToolsError> {
+        let request = self.request(ti)?;
+        let response = ti.execute_checked(&request).await?;
+        self.parse(&response)
     }
 }
@@ -1,53 +1,663 @@
+#[cfg(feature = "tokio")]
+use crate::Site;
+use crate::ToolsError;
 /// # ToolsInterface
 /// Some common helper functions for interacting with the Wikimedia Toolforge environment,
 /// e.g. creating HTTP clients, getting Wikidata item ID for page titles, etc.
 ///
+/// A `ToolsInterface` owns the HTTP client configuration (timeout, user agent, proxy, default headers)
+/// and the clients built from it, so connection pools and TLS sessions are shared between requests.
+/// Cloning a `ToolsInterface` is cheap, and all clones share the same clients.
+/// MediaWiki `Api` objects are created once per site and shared as well, see `ToolsInterface::api`.
+/// All requests are sent through a `Transport`; by default `ReqwestTransport`, which uses the shared clients.
+/// A process-wide instance is used by default; pass your own to `Tool::run_with` to override it.
+///
 /// ## Example
 /// ```ignore
 /// let result = ToolsInterface::wikidata_item_for_titles(wiki, ["Albert Einstein".to_string()]).await.unwrap();
 /// let q = result.get("Albert Einstein").unwrap(); // Yields "Q937"
+///
+/// // Configure the clients used by all tools
+/// let ti = ToolsInterface::builder()
+///     .timeout(Duration::from_secs(60))
+///     .default_header("X-Foo", "bar")
+///     .build()
+///     .unwrap();
+/// ToolsInterface::set_global(ti);
+///
+/// // Identify your tool, as asked for by the Wikimedia User-Agent policy
+/// let ti = ToolsInterface::builder()
+///     .application("my_bot", "1.2")
+///     .contact("https://example.org/my_bot")
+///     .build()
+///     .unwrap(); // User-Agent: my_bot/1.2 (https://example.org/my_bot) tools_interface/0.1.14
+///
+/// // Send all requests through your own transport, e.g. an in-process fake
+/// let ti = ToolsInterface::builder().transport(MyFakeTransport::new()).build().unwrap();
 /// ```
-use crate::ToolsError;
+use crate::cache::ResponseCache;
+use crate::endpoint::{Endpoint, Endpoints};
+use crate::rate_limit::{RateLimit, RateLimiter};
+use crate::recorder::Recorder;
+use crate::request::{ToolRequest, ToolResponse};
+use crate::request_trace::RequestTrace;
+#[cfg(feature = "tokio")]
+use crate::request_trace::trace_api;
+use crate::retry::RetryPolicy;
+use crate::transport::{ClientConfig, ReqwestTransport, Transport};
+use lazy_static::lazy_static;
+#[cfg(feature = "tokio")]
 use mediawiki::api::Api;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use std::collections::HashMap;
-use std::time::Duration;
+#[cfg(feature = "tokio")]
+use std::sync::Mutex;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 const DEFAULT_CLIENT_TIMEOUT_SECONDS: u64 = 300; // 5min
+const WIKIDATA_HOST: &str = "www.wikidata.org";
+#[cfg(feature = "tokio")]
+const COMMONS_HOST: &str = "commons.wikimedia.org";
 
 pub static TOOLS_INTERFACE_USER_AGENT: &str =
     concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
-pub struct ToolsInterface {}
+lazy_static! {
+    static ref GLOBAL_TOOLS_INTERFACE: RwLock<Option<ToolsInterface>> = RwLock::new(None);
+}
+
+/// Builds a `ToolsInterface` with a custom HTTP client configuration.
+#[derive(Debug, Clone)]
+pub struct ToolsInterfaceBuilder {
+    user_agent: Option<String>,
+    application: Option<(String, String)>,
+    contact: Option<String>,
+    timeout: Duration,
+    proxy: Option<String>,
+    default_headers: Vec<(String, String)>,
+    endpoints: Endpoints,
+    retry_policy: RetryPolicy,
+    default_rate_limit: RateLimit,
+    rate_limits: HashMap<String, RateLimit>,
+    cache: Option<Arc<dyn ResponseCache>>,
+    cache_ttls: HashMap<Endpoint, Duration>,
+    host_overrides: HashMap<String, String>,
+    recorder: Option<Arc<Recorder>>,
+    transport: Option<Arc<dyn Transport>>,
+}
+
+impl Default for ToolsInterfaceBuilder {
+    fn default() -> Self {
+        Self {
+            user_agent: None,
+            application: None,
+            contact: None,
+            timeout: Duration::from_secs(DEFAULT_CLIENT_TIMEOUT_SECONDS),
+            proxy: None,
+            default_headers: vec![],
+            endpoints: Endpoints::default(),
+            retry_policy: RetryPolicy::default(),
+            default_rate_limit: RateLimit::default(),
+            rate_limits: HashMap::new(),
+            cache: None,
+            cache_ttls: HashMap::new(),
+            host_overrides: HashMap::new(),
+            recorder: None,
+            transport: None,
+        }
+    }
+}
+
+impl ToolsInterfaceBuilder {
+    /// Sets the complete User-Agent header for all requests.
+    /// This overrides the User-Agent composed from `application` and `contact`.
+    pub fn user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Identifies the application using this crate in the User-Agent, e.g. `("my_bot", "1.2")`.
+    pub fn application<S1: Into<String>, S2: Into<String>>(
+        mut self,
+        name: S1,
+        version: S2,
+    ) -> Self {
+        self.application = Some((name.into(), version.into()));
+        self
+    }
+
+    /// Sets contact information for the User-Agent, e.g. a URL or an email address,
+    /// as asked for by the [Wikimedia User-Agent policy](https://foundation.wikimedia.org/wiki/Policy:Wikimedia_Foundation_User-Agent_Policy).
+    pub fn contact<S: Into<String>>(mut self, contact: S) -> Self {
+        self.contact = Some(contact.into());
+        self
+    }
+
+    /// Composes the User-Agent as `<application>/<version> (<contact>) tools_interface/<version>`.
+    /// Without application and contact, this is `TOOLS_INTERFACE_USER_AGENT`.
+    fn compose_user_agent(&self) -> String {
+        if let Some(user_agent) = &self.user_agent {
+            return user_agent.to_owned();
+        }
+        let contact = self.contact.as_ref().map(|contact| format!(" ({contact})"));
+        match &self.application {
+            Some((name, version)) => format!(
+                "{name}/{version}{} {TOOLS_INTERFACE_USER_AGENT}",
+                contact.unwrap_or_default()
+            ),
+            None => format!(
+                "{TOOLS_INTERFACE_USER_AGENT}{}",
+                contact.unwrap_or_default()
+            ),
+        }
+    }
+
+    /// Sets the total timeout for a single request. Default is 5 minutes.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Routes all requests through the given proxy URL.
+    pub fn proxy<S: Into<String>>(mut self, proxy: S) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Adds a header that is sent with every request.
+    pub fn default_header<S1: Into<String>, S2: Into<String>>(
+        mut self,
+        name: S1,
+        value: S2,
+    ) -> Self {
+        self.default_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Overrides the base URL of a tool, e.g. to use a mirror or a mock server.
+    pub fn endpoint<S: Into<String>>(mut self, endpoint: Endpoint, base_url: S) -> Self {
+        self.endpoints.set(endpoint, base_url);
+        self
+    }
+
+    /// Sets the retry policy for all requests.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets the rate limit for a host, e.g. "petscan.wmflabs.org".
+    pub fn rate_limit<S: Into<String>>(mut self, host: S, rate_limit: RateLimit) -> Self {
+        self.rate_limits.insert(host.into(), rate_limit);
+        self
+    }
+
+    /// Sets the rate limit for all hosts without their own limit. Default is unlimited.
+    pub fn default_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.default_rate_limit = rate_limit;
+        self
+    }
+
+    /// Sets the response cache. Responses are only cached for endpoints with a TTL, see `cache_ttl`.
+    pub fn cache<C: ResponseCache + 'static>(mut self, cache: C) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Sets how long responses from a tool are cached.
+    pub fn cache_ttl(mut self, endpoint: Endpoint, ttl: Duration) -> Self {
+        self.cache_ttls.insert(endpoint, ttl);
+        self
+    }
+
+    /// Sends all requests for a host, e.g. "en.wikipedia.org", to another base URL instead, e.g. a mock server.
+    /// Unlike `endpoint`, this also applies to URLs that are not tool endpoints, like wiki APIs.
+    /// The path and query of the request are appended to the base URL.
+    pub fn host_override<S1: Into<String>, S2: Into<String>>(
+        mut self,
+        host: S1,
+        base_url: S2,
+    ) -> Self {
+        let base_url: String = base_url.into();
+        let base_url = base_url.trim_end_matches('/').to_string();
+        self.host_overrides.insert(host.into(), base_url);
+        self
+    }
+
+    /// Records all requests and their responses, e.g. to save them as a test fixture.
+    pub fn recorder(mut self, recorder: Arc<Recorder>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Sends all requests through the given transport, instead of the default `ReqwestTransport`.
+    pub fn transport<T: Transport + 'static>(mut self, transport: T) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Returns a `ReqwestTransport` with the client configuration of this builder,
+    /// e.g. to be wrapped by a custom `transport`.
+    pub fn reqwest_transport(&self) -> Result<ReqwestTransport, ToolsError> {
+        ReqwestTransport::new(self.client_config()?)
+    }
+
+    /// Validates the configuration and builds the `ToolsInterface`.
+    pub fn build(self) -> Result<ToolsInterface, ToolsError> {
+        let reqwest = Arc::new(self.reqwest_transport()?);
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => reqwest.clone(),
+        };
+        Ok(ToolsInterface {
+            inner: Arc::new(ToolsInterfaceInner {
+                reqwest,
+                transport,
+                #[cfg(feature = "tokio")]
+                apis: Mutex::new(HashMap::new()),
+                endpoints: self.endpoints,
+                retry_policy: self.retry_policy,
+                rate_limiter: RateLimiter::new(self.default_rate_limit, self.rate_limits),
+                cache: self.cache,
+                cache_ttls: self.cache_ttls,
+                host_overrides: self.host_overrides,
+                recorder: self.recorder,
+            }),
+            force_refresh: false,
+        })
+    }
+
+    fn client_config(&self) -> Result<ClientConfig, ToolsError> {
+        let user_agent = self.compose_user_agent();
+        HeaderValue::from_str(&user_agent)
+            .map_err(|e| ToolsError::Tool(format!("Invalid User-Agent '{user_agent}': {e}")))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.default_headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| ToolsError::Tool(format!("Invalid header name '{name}': {e}")))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| ToolsError::Tool(format!("Invalid header value '{value}': {e}")))?;
+            headers.insert(name, value);
+        }
+        let proxy = match &self.proxy {
+            Some(url) => Some(reqwest::Proxy::all(url)?),
+            None => None,
+        };
+        Ok(ClientConfig {
+            user_agent,
+            timeout: self.timeout,
+            proxy,
+            headers,
+        })
+    }
+}
+
+#[cfg(feature = "tokio")]
+/// A lazily created, shared MediaWiki API object.
+type ApiSlot = Arc<futures::lock::Mutex<Option<Arc<Api>>>>;
+
+#[derive(Debug)]
+struct ToolsInterfaceInner {
+    reqwest: Arc<ReqwestTransport>,
+    transport: Arc<dyn Transport>,
+    endpoints: Endpoints,
+    retry_policy: RetryPolicy,
+    rate_limiter: RateLimiter,
+    cache: Option<Arc<dyn ResponseCache>>,
+    cache_ttls: HashMap<Endpoint, Duration>,
+    host_overrides: HashMap<String, String>,
+    recorder: Option<Arc<Recorder>>,
+    #[cfg(feature = "tokio")]
+    // One slot per site; the async lock makes concurrent callers wait for a single `Api::new`
+    apis: Mutex<HashMap<Site, ApiSlot>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolsInterface {
+    inner: Arc<ToolsInterfaceInner>,
+    force_refresh: bool,
+}
 
 impl ToolsInterface {
+    /// Returns a builder for a custom `ToolsInterface`.
+    pub fn builder() -> ToolsInterfaceBuilder {
+        ToolsInterfaceBuilder::default()
+    }
+
+    /// Returns the process-wide `ToolsInterface`, creating it with default settings if necessary.
+    pub fn global() -> Self {
+        if let Some(ti) = GLOBAL_TOOLS_INTERFACE
+            .read()
+            .ok()
+            .and_then(|global| global.clone())
+        {
+            return ti;
+        }
+        let mut global = GLOBAL_TOOLS_INTERFACE
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        global
+            .get_or_insert_with(|| {
+                Self::builder()
+                    .build()
+                    .expect("Default ToolsInterface should build")
+            })
+            .clone()
+    }
+
+    /// Replaces the process-wide `ToolsInterface` used by all tools that are not given one explicitly.
+    pub fn set_global(ti: ToolsInterface) {
+        let mut global = GLOBAL_TOOLS_INTERFACE
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        *global = Some(ti);
+    }
+
+    /// Returns the User-Agent used by the clients.
+    pub fn user_agent(&self) -> &str {
+        &self.inner.reqwest.config().user_agent
+    }
+
+    /// Returns the base URL for a tool, without trailing slash.
+    pub fn endpoint(&self, endpoint: Endpoint) -> &str {
+        self.inner.endpoints.url(endpoint)
+    }
+
+    /// Returns the endpoint registry.
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.inner.endpoints
+    }
+
+    /// Returns the retry policy.
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.inner.retry_policy
+    }
+
+    /// Returns the per-host rate limiter.
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.inner.rate_limiter
+    }
+
+    /// Returns the response cache, if any.
+    pub fn cache(&self) -> Option<&dyn ResponseCache> {
+        self.inner.cache.as_deref()
+    }
+
+    /// Returns a `ToolsInterface` sharing this one's clients and cache,
+    /// that ignores cached responses but still stores fresh ones.
+    pub fn force_refresh(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            force_refresh: true,
+        }
+    }
+
+    /// Returns the cache and TTL for a request, if its response should be cached.
+    fn cache_for(&self, request: &ToolRequest) -> Option<(&dyn ResponseCache, Duration)> {
+        let cache = self.inner.cache.as_deref()?;
+        let endpoint = self.inner.endpoints.endpoint_for_url(request.url())?;
+        let ttl = self.inner.cache_ttls.get(&endpoint)?;
+        Some((cache, *ttl))
+    }
+
+    fn cached_response(&self, request: &ToolRequest) -> Option<ToolResponse> {
+        if self.force_refresh {
+            return None;
+        }
+        let (cache, _ttl) = self.cache_for(request)?;
+        cache.get(&request.cache_key())
+    }
+
+    fn store_response(&self, request: &ToolRequest, result: &Result<ToolResponse, ToolsError>) {
+        if let (Some((cache, ttl)), Ok(response)) = (self.cache_for(request), result)
+            && response.is_success()
+        {
+            // A failure to cache does not invalidate the response
+            let _ = cache.put(&request.cache_key(), response, ttl);
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Sends a request, retrying according to the retry policy.
+    /// Returns the last response, even if its status indicates an error.
+    /// Uses the response cache, if configured for the tool.
+    pub async fn execute(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        let trace = RequestTrace::start(request, || self.tool_name(request));
+        if let Some(response) = self.cached_response(request) {
+            trace.cached(&response);
+            return Ok(response);
+        }
+        let mut attempt = 1;
+        loop {
+            let started = Instant::now();
+            let result = self.send_once(request).await;
+            trace.attempt(attempt, started.elapsed(), &result);
+            match self
+                .inner
+                .retry_policy
+                .retry_delay(request, attempt, &result)
+            {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => {
+                    trace.finish(attempt, &result);
+                    self.store_response(request, &result);
+                    return result;
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Same as `execute`, but returns an error if the final response does not have a 2xx status.
+    pub async fn execute_checked(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        let response = self.execute(request).await?;
+        response.error_for_status(&self.tool_name(request), request.url())
+    }
+
+    /// Describes the tool a request is sent to, for error messages.
+    fn tool_name(&self, request: &ToolRequest) -> String {
+        match self.inner.endpoints.endpoint_for_url(request.url()) {
+            Some(endpoint) => format!("{endpoint:?}"),
+            None => request.host().unwrap_or_default(),
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn send_once(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        let _permit = match request.host() {
+            Some(host) => Some(self.inner.rate_limiter.acquire(&host).await),
+            None => None,
+        };
+        let response = self.inner.transport.send(&self.prepared(request)).await?;
+        self.record(request, &response);
+        Ok(response)
+    }
+
     #[cfg(feature = "blocking")]
+    /// Sends a request in a blocking manner, retrying according to the retry policy.
+    /// Returns the last response, even if its status indicates an error.
+    /// Uses the response cache, if configured for the tool.
+    pub fn execute_blocking(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        let trace = RequestTrace::start(request, || self.tool_name(request));
+        if let Some(response) = self.cached_response(request) {
+            trace.cached(&response);
+            return Ok(response);
+        }
+        let mut attempt = 1;
+        loop {
+            let started = Instant::now();
+            let result = self.send_once_blocking(request);
+            trace.attempt(attempt, started.elapsed(), &result);
+            match self
+                .inner
+                .retry_policy
+                .retry_delay(request, attempt, &result)
+            {
+                Some(delay) => std::thread::sleep(delay),
+                None => {
+                    trace.finish(attempt, &result);
+                    self.store_response(request, &result);
+                    return result;
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `execute_blocking`, but returns an error if the final response does not have a 2xx status.
+    pub fn execute_checked_blocking(
+        &self,
+        request: &ToolRequest,
+    ) -> Result<ToolResponse, ToolsError> {
+        let response = self.execute_blocking(request)?;
+        response.error_for_status(&self.tool_name(request), request.url())
+    }
+
+    #[cfg(feature = "blocking")]
+    fn send_once_blocking(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        let _permit = request
+            .host()
+            .map(|host| self.inner.rate_limiter.acquire_blocking(&host));
+        let response = self
+            .inner
+            .transport
+            .send_blocking(&self.prepared(request))?;
+        self.record(request, &response);
+        Ok(response)
+    }
+
+    /// Returns the request with its URL changed, if there is a host override for it.
+    fn routed(&self, request: &ToolRequest) -> Option<ToolRequest> {
+        if self.inner.host_overrides.is_empty() {
+            return None;
+        }
+        let url = reqwest::Url::parse(request.url()).ok()?;
+        let base_url = self.inner.host_overrides.get(url.host_str()?)?;
+        let new_url = match url.query() {
+            Some(query) => format!("{base_url}{}?{query}", url.path()),
+            None => format!("{base_url}{}", url.path()),
+        };
+        Some(request.with_url(new_url))
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Returns the URL changed according to the host overrides, for clients not using `execute`.
+    fn routed_url(&self, url: &str) -> String {
+        match self.routed(&ToolRequest::get(url)) {
+            Some(request) => request.url().to_string(),
+            None => url.to_string(),
+        }
+    }
+
+    /// Returns the request as it is passed to the transport:
+    /// routed according to the host overrides, with the User-Agent and default headers added.
+    /// Headers set on the request itself take precedence.
+    fn prepared(&self, request: &ToolRequest) -> ToolRequest {
+        let mut prepared = self.routed(request).unwrap_or_else(|| request.to_owned());
+        let config = self.inner.reqwest.config();
+        let defaults = config
+            .headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+            .chain(std::iter::once(("User-Agent", config.user_agent.as_str())));
+        for (name, value) in defaults {
+            let is_set = prepared
+                .headers()
+                .iter()
+                .any(|(k, _)| k.eq_ignore_ascii_case(name));
+            if !is_set {
+                prepared = prepared.header(name, value);
+            }
+        }
+        prepared
+    }
+
+    fn record(&self, request: &ToolRequest, response: &ToolResponse) {
+        if let Some(recorder) = &self.inner.recorder {
+            recorder.record(request, response);
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Returns the shared async HTTP client.
+    /// Requests sent with it bypass the transport, cache, retries, rate limits and host overrides.
+    #[deprecated(note = "send a `ToolRequest` with `execute` instead")]
+    pub fn client(&self) -> reqwest::Client {
+        self.inner.reqwest.client()
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Returns the shared blocking HTTP client.
+    /// Requests sent with it bypass the transport, cache, retries, rate limits and host overrides.
+    #[deprecated(note = "send a `ToolRequest` with `execute_blocking` instead")]
+    pub fn client_blocking(&self) -> Result<reqwest::blocking::Client, ToolsError> {
+        self.inner.reqwest.client_blocking()
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Returns the blocking HTTP client of the process-wide `ToolsInterface`.
+    #[deprecated(note = "send a `ToolRequest` with `execute_blocking` instead")]
     pub fn blocking_client() -> Result<reqwest::blocking::Client, ToolsError> {
-        Ok(reqwest::blocking::Client::builder()
-            .user_agent(TOOLS_INTERFACE_USER_AGENT)
-            .timeout(Duration::from_secs(DEFAULT_CLIENT_TIMEOUT_SECONDS))
-            .build()?)
+        Self::global().inner.reqwest.client_blocking()
     }
 
     #[cfg(feature = "tokio")]
+    /// Returns the async HTTP client of the process-wide `ToolsInterface`.
+    #[deprecated(note = "send a `ToolRequest` with `execute` instead")]
     pub fn tokio_client() -> Result<reqwest::Client, ToolsError> {
-        Ok(reqwest::Client::builder()
-            .user_agent(TOOLS_INTERFACE_USER_AGENT)
-            .timeout(Duration::from_secs(DEFAULT_CLIENT_TIMEOUT_SECONDS))
-            .build()?)
+        Ok(Self::global().inner.reqwest.client())
     }
 
     #[cfg(feature = "tokio")]
-    /// Returns a MediaWiki API object for Wikidata.
-    pub async fn wikidata_api() -> Result<Api, ToolsError> {
-        let api = Api::new("https://www.wikidata.org/w/api.php").await?;
+    /// Returns the MediaWiki API object for a site.
+    /// It is created on first use, and shared by all clones of this `ToolsInterface` afterwards.
+    /// Its requests use the User-Agent of this `ToolsInterface`, except for the site info request sent on creation:
+    /// the `mediawiki` crate sets its own User-Agent header on that one, which takes precedence over the client's.
+    pub async fn api(&self, site: &Site) -> Result<Arc<Api>, ToolsError> {
+        let slot = self
+            .inner
+            .apis
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(site.to_owned())
+            .or_default()
+            .clone();
+        let mut slot = slot.lock().await;
+        if let Some(api) = slot.as_ref() {
+            return Ok(api.clone());
+        }
+        let _permit = self.rate_limiter().acquire(&site.webserver()).await;
+        let api_url = self.routed_url(&site.api_url());
+        let builder = self.inner.reqwest.config().tokio_client_builder();
+        let mut api = trace_api(&api_url, Api::new_from_builder(&api_url, builder)).await?;
+        api.set_user_agent(self.user_agent());
+        let api = Arc::new(api);
+        *slot = Some(api.clone());
         Ok(api)
     }
 
     #[cfg(feature = "tokio")]
-    /// Returns a MediaWiki API object for Wikimedia Commons.
-    pub async fn commons_api() -> Result<Api, ToolsError> {
-        let api = Api::new("https://commons.wikimedia.org/w/api.php").await?;
-        Ok(api)
+    /// Returns the shared MediaWiki API object for Wikidata.
+    pub async fn wikidata_api() -> Result<Arc<Api>, ToolsError> {
+        Self::global()
+            .api(&Self::site_for_host(WIKIDATA_HOST)?)
+            .await
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Returns the shared MediaWiki API object for Wikimedia Commons.
+    pub async fn commons_api() -> Result<Arc<Api>, ToolsError> {
+        Self::global()
+            .api(&Self::site_for_host(COMMONS_HOST)?)
+            .await
+    }
+
+    #[cfg(feature = "tokio")]
+    fn site_for_host(host: &str) -> Result<Site, ToolsError> {
+        Site::from_webserver(host).ok_or_else(|| ToolsError::Tool(format!("Unknown site {host}")))
     }
 
     #[cfg(feature = "tokio")]
@@ -57,54 +667,59 @@ impl ToolsInterface {
         wiki: &str,
         titles: &[String],
     ) -> Result<HashMap<String, String>, ToolsError> {
-        use futures::stream::StreamExt;
-
-        const MAX_CONCURRENT: usize = 5;
+        Self::global()
+            .wikidata_item_for_titles_with(wiki, titles)
+            .await
+    }
 
-        let api_params =
-            Self::generate_api_params_for_wikidata_item_for_titles(titles, wiki).await?;
-        let futures = api_params
-            .iter()
-            .map(|(api, params)| api.get_query_api_json(params));
-        let stream = futures::stream::iter(futures).buffered(MAX_CONCURRENT);
-        let results = stream.collect::<Vec<_>>().await;
+    #[cfg(feature = "tokio")]
+    /// Same as `wikidata_item_for_titles`, using this `ToolsInterface`.
+    /// The requests are sent concurrently, as far as the rate limit for Wikidata allows.
+    pub async fn wikidata_item_for_titles_with(
+        &self,
+        wiki: &str,
+        titles: &[String],
+    ) -> Result<HashMap<String, String>, ToolsError> {
+        let requests = Self::wikidata_item_for_titles_requests(wiki, titles);
+        let futures = requests.iter().map(|request| self.execute_checked(request));
+        let results = futures::future::join_all(futures).await;
         let mut ret = HashMap::new();
         for result in results {
-            let result = result?;
-            let entities = result["entities"]
-                .as_object()
-                .ok_or_else(|| ToolsError::Json("['entities'] is not an object".into()))?;
-            for (id, v) in entities.iter() {
-                let sitelinks = v
-                    .get("sitelinks")
-                    .ok_or_else(|| ToolsError::Json("['sitelinks'] does not exist".into()))?
-                    .as_object()
-                    .ok_or_else(|| ToolsError::Json("['sitelinks'] is not an object".into()))?;
-                let sitelink = sitelinks
-                    .get(wiki)
-                    .ok_or_else(|| ToolsError::Json("site link not found".into()))?;
-                let title = sitelink
-                    .get("title")
-                    .ok_or_else(|| ToolsError::Json("['title'] does not exist".into()))?
-                    .as_str()
-                    .ok_or_else(|| ToolsError::Json("['title'] is not a string".into()))?;
-                ret.insert(title.replace('_', " ").to_string(), id.to_string());
-            }
+            Self::parse_wikidata_item_for_titles(wiki, &result?.json()?, &mut ret)?;
         }
         Ok(ret)
     }
 
-    async fn generate_api_params_for_wikidata_item_for_titles(
+    #[cfg(feature = "blocking")]
+    /// Same as `wikidata_item_for_titles`, in a blocking manner.
+    pub fn wikidata_item_for_titles_blocking(
+        wiki: &str,
         titles: &[String],
+    ) -> Result<HashMap<String, String>, ToolsError> {
+        Self::global().wikidata_item_for_titles_blocking_with(wiki, titles)
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Same as `wikidata_item_for_titles_blocking`, using this `ToolsInterface`.
+    pub fn wikidata_item_for_titles_blocking_with(
+        &self,
         wiki: &str,
-    ) -> Result<Vec<(std::sync::Arc<Api>, HashMap<String, String>)>, ToolsError> {
-        use std::sync::Arc;
-        let api = Arc::new(Self::wikidata_api().await?);
-        let api_params: Vec<_> = titles
+        titles: &[String],
+    ) -> Result<HashMap<String, String>, ToolsError> {
+        let mut ret = HashMap::new();
+        for request in Self::wikidata_item_for_titles_requests(wiki, titles) {
+            let result = self.execute_checked_blocking(&request)?.json()?;
+            Self::parse_wikidata_item_for_titles(wiki, &result, &mut ret)?;
+        }
+        Ok(ret)
+    }
+
+    fn wikidata_item_for_titles_requests(wiki: &str, titles: &[String]) -> Vec<ToolRequest> {
+        titles
             .chunks(50)
             .map(|chunk| {
                 let chunk = chunk.join("|");
-                let params: HashMap<String, String> = [
+                let params: Vec<(String, String)> = [
                     ("action", "wbgetentities"),
                     ("format", "json"),
                     ("sites", wiki),
@@ -115,16 +730,186 @@ impl ToolsInterface {
                 .iter()
                 .map(|(k, v)| (k.to_string(), v.to_string()))
                 .collect();
-                (api.clone(), params)
+                ToolRequest::get(format!("https://{WIKIDATA_HOST}/w/api.php")).query(&params)
             })
-            .collect();
-        Ok(api_params)
+            .collect()
+    }
+
+    fn parse_wikidata_item_for_titles(
+        wiki: &str,
+        result: &serde_json::Value,
+        ret: &mut HashMap<String, String>,
+    ) -> Result<(), ToolsError> {
+        let entities = result["entities"]
+            .as_object()
+            .ok_or_else(|| ToolsError::parse("Wikidata", "entities", "not an object"))?;
+        for (id, v) in entities.iter() {
+            let sitelinks = v
+                .get("sitelinks")
+                .ok_or_else(|| ToolsError::parse("Wikidata", "sitelinks", "missing"))?
+                .as_object()
+                .ok_or_else(|| ToolsError::parse("Wikidata", "sitelinks", "not an object"))?;
+            let sitelink = sitelinks
+                .get(wiki)
+                .ok_or_else(|| ToolsError::parse("Wikidata", "sitelinks", "site link not found"))?;
+            let title = sitelink
+                .get("title")
+                .ok_or_else(|| ToolsError::parse("Wikidata", "title", "missing"))?
+                .as_str()
+                .ok_or_else(|| ToolsError::parse("Wikidata", "title", "not a string"))?;
+            ret.insert(title.replace('_', " ").to_string(), id.to_string());
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::replay::Replay;
+
+    #[test]
+    fn test_builder() {
+        let ti = ToolsInterface::builder()
+            .user_agent("my_tool/1.0")
+            .timeout(Duration::from_secs(10))
+            .default_header("X-Foo", "bar")
+            .build()
+            .unwrap();
+        assert_eq!(ti.user_agent(), "my_tool/1.0");
+        assert!(
+            ToolsInterface::builder()
+                .default_header("Not a header", "bar")
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_user_agent() {
+        let ti = ToolsInterface::builder()
+            .application("my_bot", "1.2")
+            .contact("https://example.org/my_bot")
+            .build()
+            .unwrap();
+        assert_eq!(
+            ti.user_agent(),
+            format!("my_bot/1.2 (https://example.org/my_bot) {TOOLS_INTERFACE_USER_AGENT}")
+        );
+        let ti = ToolsInterface::builder()
+            .contact("me@example.org")
+            .build()
+            .unwrap();
+        assert_eq!(
+            ti.user_agent(),
+            format!("{TOOLS_INTERFACE_USER_AGENT} (me@example.org)")
+        );
+        let ti = ToolsInterface::builder().build().unwrap();
+        assert_eq!(ti.user_agent(), TOOLS_INTERFACE_USER_AGENT);
+        assert!(
+            ToolsInterface::builder()
+                .contact("line\nbreak")
+                .build()
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_execute_retries() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api.php"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api.php"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("OK"))
+            .mount(&mock_server)
+            .await;
+        let policy = RetryPolicy::default().with_initial_backoff(Duration::from_millis(10));
+        let ti = ToolsInterface::builder()
+            .retry_policy(policy)
+            .build()
+            .unwrap();
+        let request = ToolRequest::get(format!("{}/api.php", mock_server.uri()));
+        let response = ti.execute(&request).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text(), "OK");
+
+        let ti = ToolsInterface::builder()
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        Mock::given(method("GET"))
+            .and(path("/fail.php"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&mock_server)
+            .await;
+        let request = ToolRequest::get(format!("{}/fail.php", mock_server.uri()));
+        let response = ti.execute(&request).await.unwrap();
+        assert_eq!(response.status(), 503);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_execute_post_not_retried() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api.php"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let policy = RetryPolicy::default().with_initial_backoff(Duration::from_millis(10));
+        let ti = ToolsInterface::builder()
+            .retry_policy(policy)
+            .build()
+            .unwrap();
+        let request = ToolRequest::post(format!("{}/api.php", mock_server.uri()));
+        let response = ti.execute(&request).await.unwrap();
+        assert_eq!(response.status(), 503);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_execute_cache() {
+        use crate::MemoryCache;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api.php"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("OK"))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .endpoint(Endpoint::Quarry, mock_server.uri())
+            .cache(MemoryCache::new())
+            .cache_ttl(Endpoint::Quarry, Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let request = ToolRequest::get(format!("{}/api.php", mock_server.uri()));
+        assert_eq!(ti.execute(&request).await.unwrap().text(), "OK");
+        assert_eq!(ti.execute(&request).await.unwrap().text(), "OK"); // Cached
+        assert_eq!(
+            ti.force_refresh().execute(&request).await.unwrap().text(),
+            "OK"
+        );
+        // Mock server verifies the expected number of requests on drop
+    }
 
     #[cfg(feature = "tokio")]
     #[tokio::test]
@@ -136,7 +921,9 @@ mod tests {
             "Johannes Kepler".to_string(),
         ];
 
-        let result = ToolsInterface::wikidata_item_for_titles(wiki, &titles)
+        let replay = Replay::start("wikidata_item_for_titles").await;
+        let result = replay
+            .wikidata_item_for_titles_with(wiki, &titles)
             .await
             .unwrap();
         assert_eq!(result.len(), 3);
@@ -144,4 +931,84 @@ mod tests {
         assert_eq!(result.get("Isaac Newton"), Some(&"Q935".to_string()));
         assert_eq!(result.get("Johannes Kepler"), Some(&"Q8963".to_string()));
     }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_wikidata_item_for_titles_max_in_flight() {
+        use wiremock::matchers::query_param;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(query_param("action", "wbgetentities"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"entities":{}}))
+                    .set_delay(Duration::from_millis(50)),
+            )
+            .expect(3)
+            .mount(&server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .host_override(WIKIDATA_HOST, server.uri())
+            .rate_limit(WIKIDATA_HOST, RateLimit::default().with_max_in_flight(1))
+            .build()
+            .unwrap();
+        let titles: Vec<String> = (0..120).map(|i| format!("Title {i}")).collect();
+        let started = Instant::now();
+        let result = ti
+            .wikidata_item_for_titles_with("dewiki", &titles)
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+        // One request at a time
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_api_user_agent() {
+        use wiremock::matchers::{method, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("meta", "siteinfo"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({"query":{"general":{"sitename":"Wikidata"},"namespaces":{}}}),
+            ))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(query_param("meta", "userinfo"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({"query":{"userinfo":{"id":0,"name":"127.0.0.1"}}}),
+            ))
+            .mount(&mock_server)
+            .await;
+        let ti = ToolsInterface::builder()
+            .user_agent("my_tool/1.0")
+            .host_override("www.wikidata.org", mock_server.uri())
+            .build()
+            .unwrap();
+        let api = ti
+            .api(&Site::from_wiki("wikidatawiki").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(api.user_agent(), "my_tool/1.0");
+        let params = api.params_into(&[("action", "query"), ("meta", "userinfo")]);
+        api.get_query_api_json(&params).await.unwrap();
+        let requests = mock_server.received_requests().await.unwrap();
+        let user_agent = requests.last().unwrap().headers.get("user-agent").unwrap();
+        assert!(user_agent.to_str().unwrap().starts_with("my_tool/1.0"));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_api_shared() {
+        let replay = Replay::start("wikidata_siteinfo").await;
+        let site = Site::from_wiki("wikidatawiki").unwrap();
+        let api1 = replay.api(&site).await.unwrap();
+        let api2 = replay.api(&site).await.unwrap();
+        assert!(Arc::ptr_eq(&api1, &api2));
+    }
 }
@@ -0,0 +1,261 @@
+/// # Transport
+/// A `Transport` sends a `ToolRequest`, and returns the fully read `ToolResponse` (status, headers and body).
+/// Every request of a `ToolsInterface` goes through its transport, after caching, rate limiting and
+/// host overrides have been applied; retries, recording and tracing wrap around it.
+/// The default is `ReqwestTransport`, using the shared `reqwest` clients.
+/// A custom transport can add authentication headers, audit requests, or answer them in-process, e.g. in tests.
+/// It has to implement `send`; `send_blocking` falls back to running `send` on the current thread.
+/// The request passed to a transport already carries the User-Agent and default headers of the `ToolsInterface`.
+///
+/// MediaWiki `Api` objects (see `ToolsInterface::api`) use their own `reqwest` client, and bypass the transport.
+///
+/// ## Example
+/// ```ignore
+/// #[derive(Debug)]
+/// struct Auth(Arc<dyn Transport>);
+///
+/// #[async_trait]
+/// impl Transport for Auth {
+///     async fn send(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+///         self.0.send(&request.clone().header("Authorization", "Bearer 123")).await
+///     }
+/// }
+///
+/// let builder = ToolsInterface::builder().contact("https://example.org/my_bot");
+/// let reqwest = builder.reqwest_transport()?;
+/// let ti = builder.transport(Auth(Arc::new(reqwest))).build()?;
+/// ```
+use crate::{ToolRequest, ToolResponse, ToolsError};
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+#[cfg(feature = "blocking")]
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[async_trait]
+pub trait Transport: Debug + Send + Sync {
+    /// Sends a request, and reads the complete response.
+    async fn send(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError>;
+
+    /// Sends a request in a blocking manner, and reads the complete response.
+    /// Defaults to running `send` on the current thread, which is enough for in-process transports.
+    /// Override it if `send` needs an async runtime (e.g. it does network I/O with tokio):
+    /// the default blocks the calling thread, and can deadlock when called from within a runtime.
+    fn send_blocking(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        futures::executor::block_on(self.send(request))
+    }
+}
+
+/// A shared transport, e.g. one that is also used outside of the `ToolsInterface`.
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    async fn send(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        self.as_ref().send(request).await
+    }
+
+    fn send_blocking(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        self.as_ref().send_blocking(request)
+    }
+}
+
+/// The configuration of the `reqwest` clients.
+#[derive(Debug)]
+pub(crate) struct ClientConfig {
+    pub(crate) user_agent: String,
+    pub(crate) timeout: Duration,
+    pub(crate) proxy: Option<reqwest::Proxy>,
+    pub(crate) headers: HeaderMap,
+}
+
+impl ClientConfig {
+    #[cfg(feature = "tokio")]
+    pub(crate) fn tokio_client_builder(&self) -> reqwest::ClientBuilder {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(&self.user_agent)
+            .timeout(self.timeout)
+            .default_headers(self.headers.clone());
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        builder
+    }
+
+    #[cfg(feature = "blocking")]
+    fn blocking_client(&self) -> Result<reqwest::blocking::Client, ToolsError> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .user_agent(&self.user_agent)
+            .timeout(self.timeout)
+            .default_headers(self.headers.clone());
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// The default transport, sending requests over HTTP with `reqwest`.
+#[derive(Debug)]
+pub struct ReqwestTransport {
+    #[cfg(feature = "tokio")]
+    client: reqwest::Client,
+    // Built on first use; a blocking client must not be created or dropped inside an async runtime
+    #[cfg(feature = "blocking")]
+    blocking_client: Mutex<Option<reqwest::blocking::Client>>,
+    config: ClientConfig,
+}
+
+impl ReqwestTransport {
+    pub(crate) fn new(config: ClientConfig) -> Result<Self, ToolsError> {
+        Ok(Self {
+            #[cfg(feature = "tokio")]
+            client: config.tokio_client_builder().build()?,
+            #[cfg(feature = "blocking")]
+            blocking_client: Mutex::new(None),
+            config,
+        })
+    }
+
+    pub(crate) fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    #[cfg(feature = "tokio")]
+    /// Returns the async HTTP client.
+    pub fn client(&self) -> reqwest::Client {
+        self.client.clone()
+    }
+
+    #[cfg(feature = "blocking")]
+    /// Returns the blocking HTTP client, creating it on first use.
+    pub fn client_blocking(&self) -> Result<reqwest::blocking::Client, ToolsError> {
+        let mut client = self
+            .blocking_client
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = client.as_ref() {
+            return Ok(client.clone());
+        }
+        let new_client = self.config.blocking_client()?;
+        *client = Some(new_client.clone());
+        Ok(new_client)
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    #[cfg(feature = "tokio")]
+    async fn send(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        let response = request.to_reqwest(&self.client).send().await?;
+        ToolResponse::from_reqwest(response).await
+    }
+
+    // Without tokio, there is no async client; the `ToolsInterface` only sends blocking requests then
+    #[cfg(all(not(feature = "tokio"), feature = "blocking"))]
+    async fn send(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        self.send_blocking(request)
+    }
+
+    #[cfg(not(any(feature = "tokio", feature = "blocking")))]
+    async fn send(&self, _request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        Err(ToolsError::Tool(
+            "No HTTP client, enable the `tokio` or `blocking` feature".to_string(),
+        ))
+    }
+
+    #[cfg(feature = "blocking")]
+    fn send_blocking(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+        let client = self.client_blocking()?;
+        let response = request.to_reqwest_blocking(&client).send()?;
+        ToolResponse::from_reqwest_blocking(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Endpoint, MissingTopics, Site, Tool, ToolsInterface};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Answers every request with the same response, and keeps the requests.
+    #[derive(Debug)]
+    struct FakeTransport {
+        response: ToolResponse,
+        requests: Mutex<Vec<ToolRequest>>,
+    }
+
+    impl FakeTransport {
+        fn new(body: serde_json::Value) -> Self {
+            Self {
+                response: ToolResponse::new(200, vec![], body.to_string().into_bytes()),
+                requests: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Transport for FakeTransport {
+        async fn send(&self, request: &ToolRequest) -> Result<ToolResponse, ToolsError> {
+            self.requests.lock().unwrap().push(request.to_owned());
+            Ok(self.response.clone())
+        }
+    }
+
+    fn fake() -> Arc<FakeTransport> {
+        Arc::new(FakeTransport::new(
+            json!({"results":{"Zellphysiologie":4},"status":"OK","url":"https://example.org"}),
+        ))
+    }
+
+    fn ti_with(fake: &Arc<FakeTransport>) -> ToolsInterface {
+        ToolsInterface::builder()
+            .user_agent("my_tool/1.0")
+            .default_header("X-Foo", "bar")
+            .endpoint(Endpoint::MissingTopics, "https://missingtopics.example.org")
+            .transport(fake.clone())
+            .build()
+            .unwrap()
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_custom_transport() {
+        let fake = fake();
+        let ti = ti_with(&fake);
+        let mut mt =
+            MissingTopics::new(Site::from_wiki("dewiki").unwrap()).with_article("Biologie");
+        let results = mt.run_with(&ti).await.unwrap();
+        assert_eq!(results, vec![("Zellphysiologie".to_string(), 4)]);
+
+        let requests = fake.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(
+            requests[0]
+                .url()
+                .starts_with("https://missingtopics.example.org/")
+        );
+        let header = |name: &str| {
+            requests[0]
+                .headers()
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.to_owned())
+        };
+        assert_eq!(header("user-agent").as_deref(), Some("my_tool/1.0"));
+        assert_eq!(header("x-foo").as_deref(), Some("bar"));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_custom_transport_blocking() {
+        let fake = fake();
+        let ti = ti_with(&fake);
+        let mut mt =
+            MissingTopics::new(Site::from_wiki("dewiki").unwrap()).with_article("Biologie");
+        mt.run_blocking_with(&ti).unwrap();
+        assert_eq!(mt.results().len(), 1);
+        assert_eq!(fake.requests.lock().unwrap().len(), 1);
+    }
+}
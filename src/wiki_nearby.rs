@@ -16,11 +16,16 @@
 ///        println!("Page {} Description {} Lat {} Lon {} Image {}", result.title, result.description, result.lat, result.lon, result.image);
 ///     });
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, Paginated, ResultPage, Site, Tool, ToolRequest,
+    ToolResponse, ToolsError, ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
-use serde_json::{Value, json};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct WikiNearbyResult {
     pub title: String,
     pub description: String,
@@ -47,7 +52,8 @@ impl WikiNearbyResult {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WikiNearby {
     site: Site,
     query: String,
@@ -103,58 +109,113 @@ impl WikiNearby {
     pub fn query(&self) -> &str {
         &self.query
     }
-
-    pub async fn as_json(&self) -> Value {
-        let site = self.site();
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.results()
-                .iter()
-                .map(|result| (FancyTitle::from_prefixed(&result.title, &api).to_json(),result))
-                .map(|(mut v,result)| {
-                v["image"] = json!(result.image);
-                v["lat"] = json!(result.lat);
-                v["lon"] = json!(result.lon);
-                v["distance"] = json!(result.distance);
-                v
-                })
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
-    }
 }
 
 #[async_trait]
 impl Tool for WikiNearby {
-    fn get_url(&self) -> String {
+    type Output = Vec<WikiNearbyResult>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        Ok(ToolRequest::get(self.get_url(ti)))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_json(response.json()?)?;
+        Ok(self.results.clone())
+    }
+}
+
+impl WikiNearby {
+    pub fn get_url(&self, ti: &ToolsInterface) -> String {
+        self.url_at_offset(ti, self.offset)
+    }
+
+    fn url_at_offset(&self, ti: &ToolsInterface, offset: usize) -> String {
         format!(
-            "https://wikinearby.toolforge.org/api/nearby?q={query}&lang={lang}&offset={offset}",
+            "{base}/api/nearby?q={query}&lang={lang}&offset={offset}",
+            base = ti.endpoint(Endpoint::WikiNearby),
             query = self.query,
             lang = self.site.language(),
-            offset = self.offset,
         )
     }
 
     fn set_from_json(&mut self, j: Value) -> Result<(), ToolsError> {
         self.lat = WikiNearbyResult::json2f64(&j["lat"]);
         self.lon = WikiNearbyResult::json2f64(&j["lon"]);
-        for entry in j["list"]
+        self.results = Self::results_from_json(&j)?;
+        Ok(())
+    }
+
+    fn results_from_json(j: &Value) -> Result<Vec<WikiNearbyResult>, ToolsError> {
+        Ok(j["list"]
             .as_array()
-            .ok_or_else(|| ToolsError::Json("Result is not an array".to_string()))?
-        {
-            match WikiNearbyResult::from_json(entry) {
-                Some(result) => self.results.push(result),
-                None => continue,
-            }
-        }
+            .ok_or_else(|| ToolsError::parse("WikiNearby", "list", "not an array"))?
+            .iter()
+            .filter_map(WikiNearbyResult::from_json)
+            .collect())
+    }
+}
 
-        Ok(())
+/// Pages through the nearby pages, starting at `offset`, until the tool returns an empty list.
+impl Paginated for WikiNearby {
+    type Item = WikiNearbyResult;
+    type Cursor = usize;
+
+    fn page_request(
+        &self,
+        ti: &ToolsInterface,
+        cursor: Option<&usize>,
+    ) -> Result<ToolRequest, ToolsError> {
+        Ok(ToolRequest::get(
+            self.url_at_offset(ti, cursor.copied().unwrap_or(self.offset)),
+        ))
+    }
+
+    fn parse_page(
+        &self,
+        response: &ToolResponse,
+        cursor: Option<&usize>,
+    ) -> Result<ResultPage<WikiNearbyResult, usize>, ToolsError> {
+        let j = response.json()?;
+        // The list length is not reduced by results that can not be parsed
+        let returned = j["list"].as_array().map_or(0, |list| list.len());
+        let offset = cursor.copied().unwrap_or(self.offset);
+        Ok(ResultPage {
+            items: Self::results_from_json(&j)?,
+            next: Some(offset + returned).filter(|_| returned > 0),
+        })
+    }
+}
+
+impl PageListSource for WikiNearby {
+    fn page_list_site(&self) -> Option<Site> {
+        Some(self.site.clone())
+    }
+
+    fn page_list_with(&self, namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let pages = self
+            .results
+            .iter()
+            .map(|result| {
+                let meta = PageMeta {
+                    description: Some(result.description.to_owned()),
+                    image: result.image.to_owned(),
+                    coordinates: Some((result.lat, result.lon)),
+                    distance: Some(result.distance),
+                    ..Default::default()
+                };
+                Page::from_prefixed(&result.title, namespaces, meta)
+            })
+            .collect();
+        Ok(PageList::new(self.site.clone(), pages))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::replay::Replay;
 
     #[test]
     fn test_new_from_page() {
@@ -173,12 +234,15 @@ mod tests {
         assert_eq!(tool.query(), "52.205, 0.1225");
     }
 
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_json() {
         let site = Site::from_wiki("enwiki").unwrap();
         let title = "Cambridge";
+        let replay = Replay::start("wiki_nearby_cambridge").await;
         let mut tool = WikiNearby::new_from_page(site, title);
-        tool.run().await.unwrap();
+        tool.run_with(&replay).await.unwrap();
+        assert_eq!(tool.lat(), Some(52.205));
         assert!(
             tool.results()
                 .iter()
@@ -15,12 +15,18 @@
 ///        println!("Page {} Item {} Description {}", result.title, result.qid, result.description);
 ///     });
 /// ```
-use crate::{Site, Tool, ToolsError, fancy_title::FancyTitle};
+use crate::{
+    Endpoint, NamespaceTable, PageListSource, Paginated, ResultPage, Site, Tool, ToolRequest,
+    ToolResponse, ToolsError, ToolsInterface,
+    page_list::{Page, PageList, PageMeta},
+};
 use async_trait::async_trait;
 use chrono::{NaiveDate, NaiveDateTime};
-use serde_json::{Value, json};
+use mediawiki::title::Title;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct XtoolsPagesResult {
     pub title: String,
     pub namespace_id: u32,
@@ -56,7 +62,8 @@ impl XtoolsPagesResult {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DeletedPages {
     #[default]
     All,
@@ -74,7 +81,8 @@ impl DeletedPages {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Redirects {
     None,
     #[default]
@@ -92,7 +100,8 @@ impl Redirects {
     }
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct XtoolsPages {
     site: Site,
     user: String,
@@ -169,32 +178,27 @@ impl XtoolsPages {
     pub fn deleted_pages(&self) -> &DeletedPages {
         &self.deleted_pages
     }
-
-    pub async fn as_json(&self) -> Value {
-        let site = self.site();
-        let api = site.api().await.unwrap();
-        json!({
-            "pages": self.results()
-                .iter()
-                .map(|result| (FancyTitle::new(&result.title, result.namespace_id as i64, &api).to_json(),result))
-                .map(|(mut v,result)| {
-                v["creation_date"] = json!(result.date.format("%Y-%m-%dT%H:%M:%SZ").to_string());
-                v["original_size"] = json!(result.original_size);
-                v["current_size"] = json!(result.current_size);
-                v["assessment"] = json!(result.assessment);
-                v
-                })
-                .collect::<Vec<Value>>(),
-            "site": site,
-        })
-    }
 }
 
 #[async_trait]
 impl Tool for XtoolsPages {
-    fn get_url(&self) -> String {
+    type Output = Vec<XtoolsPagesResult>;
+
+    fn request(&self, ti: &ToolsInterface) -> Result<ToolRequest, ToolsError> {
+        Ok(ToolRequest::get(self.get_url(ti)))
+    }
+
+    fn parse(&mut self, response: &ToolResponse) -> Result<Self::Output, ToolsError> {
+        self.set_from_text(&response.text())?;
+        Ok(self.results.clone())
+    }
+}
+
+impl XtoolsPages {
+    pub fn get_url(&self, ti: &ToolsInterface) -> String {
         let url = format!(
-            "https://xtools.wmcloud.org/pages/{server}/{user}/{namespace_id}/{redirects}/{deleted_pages}/{start_date}/{end_date}?format=tsv",
+            "{base}/pages/{server}/{user}/{namespace_id}/{redirects}/{deleted_pages}/{start_date}/{end_date}?format=tsv",
+            base = ti.endpoint(Endpoint::XtoolsPages),
             server = self.site.webserver(),
             user = self.user,
             namespace_id = self.namespace_id.unwrap_or(0),
@@ -206,31 +210,64 @@ impl Tool for XtoolsPages {
         url
     }
 
-    #[cfg(feature = "blocking")]
-    /// Run the tool in a blocking manner.
-    fn run_blocking(&mut self) -> Result<(), ToolsError> {
-        let url = self.get_url();
-        let client = crate::ToolsInterface::blocking_client()?;
-        let text = client.get(&url).send()?.text()?;
-        self.set_from_text(&text)
-    }
-
-    #[cfg(feature = "tokio")]
-    /// Run the tool asynchronously.
-    async fn run(&mut self) -> Result<(), ToolsError> {
-        let url = self.get_url();
-        let client = crate::ToolsInterface::tokio_client()?;
-        let text = client.get(&url).send().await?.text().await?;
-        self.set_from_text(&text)
+    fn set_from_text(&mut self, text: &str) -> Result<(), ToolsError> {
+        self.results = Self::results_from_text(text);
+        Ok(())
     }
 
-    fn set_from_text(&mut self, text: &str) -> Result<(), ToolsError> {
-        self.results = text
-            .split("\n")
+    fn results_from_text(text: &str) -> Vec<XtoolsPagesResult> {
+        text.split("\n")
             .skip(1)
             .filter_map(XtoolsPagesResult::from_tsv_row)
+            .collect()
+    }
+}
+
+/// The TSV export of XTools returns all pages in one response, so there is only a single page.
+impl Paginated for XtoolsPages {
+    type Item = XtoolsPagesResult;
+    type Cursor = ();
+
+    fn page_request(
+        &self,
+        ti: &ToolsInterface,
+        _cursor: Option<&()>,
+    ) -> Result<ToolRequest, ToolsError> {
+        self.request(ti)
+    }
+
+    fn parse_page(
+        &self,
+        response: &ToolResponse,
+        _cursor: Option<&()>,
+    ) -> Result<ResultPage<XtoolsPagesResult, ()>, ToolsError> {
+        Ok(ResultPage::last(Self::results_from_text(&response.text())))
+    }
+}
+
+impl PageListSource for XtoolsPages {
+    fn page_list_site(&self) -> Option<Site> {
+        Some(self.site.clone())
+    }
+
+    fn page_list_with(&self, _namespaces: &NamespaceTable) -> Result<PageList, ToolsError> {
+        let pages = self
+            .results
+            .iter()
+            .map(|result| {
+                let title = Title::new(&result.title, result.namespace_id as i64);
+                let creation_date = result.date.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+                let meta = PageMeta {
+                    size: Some(result.current_size as u64),
+                    ..Default::default()
+                }
+                .with_extra("creation_date", json!(creation_date))
+                .with_extra("original_size", json!(result.original_size))
+                .with_extra("assessment", json!(result.assessment));
+                Page::new(title, meta)
+            })
             .collect();
-        Ok(())
+        Ok(PageList::new(self.site.clone(), pages))
     }
 }
 
@@ -249,15 +286,38 @@ mod tests {
         assert_eq!(tool.end_date(), Some(end_date));
     }
 
+    #[test]
+    fn test_serde_roundtrip() {
+        let site = Site::from_wiki("enwiki").unwrap();
+        let end_date = NaiveDate::parse_from_str("2024-12-31", "%Y-%m-%d").unwrap();
+        let tool = XtoolsPages::new(site, "Magnus Manske")
+            .with_deleted_pages(DeletedPages::Live)
+            .with_end_date(end_date);
+        let j = serde_json::to_value(&tool).unwrap();
+        assert_eq!(j["user"], "Magnus Manske");
+        assert_eq!(j["deleted_pages"], "live");
+        assert_eq!(j["end_date"], "2024-12-31");
+        let tool2: XtoolsPages = serde_json::from_value(j).unwrap();
+        assert_eq!(tool, tool2);
+
+        // Missing fields use their defaults
+        let tool3: XtoolsPages = serde_json::from_value(json!({"user": "Foo"})).unwrap();
+        assert_eq!(tool3.redirects, Redirects::default());
+    }
+
+    #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn test_xtools_run() {
+        let replay = crate::replay::Replay::start("xtools_pages_magnus_manske").await;
         let site = Site::from_wiki("enwiki").unwrap();
         let user = "Magnus Manske";
         let end_date = NaiveDate::parse_from_str("2024-12-31", "%Y-%m-%d").unwrap();
         let mut tool = XtoolsPages::new(site.clone(), user)
             .with_deleted_pages(DeletedPages::All)
             .with_end_date(end_date);
-        tool.run().await.unwrap();
-        assert_eq!(tool.results().len(), 985);
+        tool.run_with(&replay).await.unwrap();
+        assert_eq!(tool.results().len(), 4);
+        assert_eq!(tool.results()[0].title, "Magnus Manske");
+        assert_eq!(tool.results()[0].current_size, 10211);
     }
 }